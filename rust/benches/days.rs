@@ -0,0 +1,67 @@
+use advent_of_code_2024::Solution;
+use advent_of_code_2024::day1::Day1;
+use advent_of_code_2024::day2::Day2;
+use advent_of_code_2024::day3::Day3;
+use advent_of_code_2024::day4::{self, Day4, Dictionary};
+use advent_of_code_2024::day5::Day5;
+use advent_of_code_2024::day6::Day6;
+use advent_of_code_2024::day7::Day7;
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::fs;
+use std::hint::black_box;
+
+fn bench_day<S: Solution>(c: &mut Criterion, day: u32) {
+    let s = fs::read_to_string(format!("inputs/day{}.txt", day)).unwrap();
+    let input = S::parse(&s).unwrap();
+    let mut group = c.benchmark_group(format!("day{}", day));
+    group.bench_function("parse", |b| b.iter(|| S::parse(black_box(&s))));
+    group.bench_function("part1", |b| b.iter(|| S::part1(black_box(&input))));
+    if S::part2(&input).is_some() {
+        group.bench_function("part2", |b| b.iter(|| S::part2(black_box(&input))));
+    }
+    group.finish();
+}
+
+fn days(c: &mut Criterion) {
+    bench_day::<Day1>(c, 1);
+    bench_day::<Day2>(c, 2);
+    bench_day::<Day3>(c, 3);
+    bench_day::<Day4>(c, 4);
+    bench_day::<Day5>(c, 5);
+    bench_day::<Day6>(c, 6);
+    bench_day::<Day7>(c, 7);
+}
+
+/// A search through a board of a few megabytes, letter by letter along rays
+/// and in parallel slabs of contiguous lines.
+fn word_search(c: &mut Criterion) {
+    // A fixed pseudo-random board, the same on every run.
+    let mut state: u64 = 1;
+    let mut s = String::new();
+    for _ in 0..2000 {
+        for _ in 0..2000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            s.push(b"XMAS"[(state >> 62) as usize] as char);
+        }
+        s.push('\n');
+    }
+    let board = Day4::parse(&s).unwrap();
+    let dictionary = Dictionary::new(&["XMAS", "MAS", "SAMXMAS"]);
+    let mut group = c.benchmark_group("word search");
+    group.bench_function("rays", |b| {
+        b.iter(|| day4::count_words(black_box(&board), &dictionary))
+    });
+    group.bench_function("slabs", |b| {
+        b.iter(|| day4::count_words_parallel(black_box(&board), &dictionary))
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = days, word_search
+}
+criterion_main!(benches);
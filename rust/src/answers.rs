@@ -0,0 +1,139 @@
+use crate::Result;
+use crate::parse::lines;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// The answers file that is read by default, relative to the crate root.
+pub const DEFAULT_PATH: &str = "answers.txt";
+
+/// Known answers, keyed by day, part and input file.
+///
+/// The file format has one answer per line, as `<day> <part> <input> <answer>`.
+/// Blank lines and lines starting with `#` are ignored.
+pub struct Answers(HashMap<(u32, u32, PathBuf), String>);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail { expected: String },
+    Unknown,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Pass => write!(f, "pass"),
+            Verdict::Fail { expected } => write!(f, "FAIL, expected {}", expected),
+            Verdict::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Drops `.` components, so that `inputs/day1.txt` and `./inputs/day1.txt`
+/// refer to the same answers.
+fn normalize(path: &Path) -> PathBuf {
+    return path
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+}
+
+impl Answers {
+    pub fn parse(s: &str) -> Result<Answers> {
+        let mut answers = HashMap::new();
+        for line in lines(s) {
+            let trimmed = line.text.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split(' ');
+            let mut next = || {
+                words
+                    .next()
+                    .ok_or_else(|| line.error("expected `<day> <part> <input> <answer>`"))
+            };
+            let day = next()?.parse()?;
+            let part = next()?.parse()?;
+            let input = normalize(Path::new(next()?.text));
+            let answer = next()?.text.to_string();
+            if let Some(word) = words.next() {
+                return Err(word.error("unexpected token"));
+            }
+            answers.insert((day, part, input), answer);
+        }
+        return Ok(Answers(answers));
+    }
+
+    pub fn check(&self, day: u32, part: u32, input: &Path, answer: &str) -> Verdict {
+        match self.0.get(&(day, part, normalize(input))) {
+            None => Verdict::Unknown,
+            Some(expected) if expected == answer => Verdict::Pass,
+            Some(expected) => Verdict::Fail {
+                expected: expected.clone(),
+            },
+        }
+    }
+
+    /// The recorded answers for a day, as `(part, input)` pairs.
+    pub fn inputs(&self, day: u32) -> impl Iterator<Item = (u32, &Path)> {
+        return self
+            .0
+            .keys()
+            .filter(move |(d, _, _)| *d == day)
+            .map(|(_, part, input)| (*part, input.as_path()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::DAYS;
+    use std::fs;
+
+    #[test]
+    fn check_against_answers() {
+        let answers = Answers::parse("# comment\n\n1 2 ./inputs/day1.txt 31\n").unwrap();
+        let input = Path::new("inputs/day1.txt");
+        assert_eq!(answers.check(1, 2, input, "31"), Verdict::Pass);
+        assert_eq!(
+            answers.check(1, 2, input, "30"),
+            Verdict::Fail {
+                expected: "31".to_string()
+            }
+        );
+        assert_eq!(answers.check(1, 1, input, "11"), Verdict::Unknown);
+    }
+
+    #[test]
+    fn malformed_line_is_reported() {
+        let err = Answers::parse("1 2 inputs/day1.txt\n").err().unwrap();
+        assert_eq!((err.line, err.column), (1, 20));
+    }
+
+    #[test]
+    fn every_recorded_answer_matches() {
+        let answers = Answers::parse(&fs::read_to_string(DEFAULT_PATH).unwrap()).unwrap();
+        for day in DAYS.iter() {
+            for (part, input) in answers.inputs(day.day) {
+                assert!(
+                    day.puzzle.parts().contains(&part),
+                    "day {} part {}",
+                    day.day,
+                    part
+                );
+                let contents = fs::read_to_string(input).unwrap();
+                let solved = day.puzzle.solve(&contents, &[part]).unwrap();
+                let answer = solved[0].1.as_deref().unwrap_or("not solved");
+                assert_eq!(
+                    answers.check(day.day, part, input, answer),
+                    Verdict::Pass,
+                    "day {} part {} on {}",
+                    day.day,
+                    part,
+                    input.display()
+                );
+            }
+        }
+    }
+}
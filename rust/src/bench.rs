@@ -0,0 +1,79 @@
+use crate::{Result, Solution};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// The results file written by `aoc bench`, relative to the crate root.
+pub const DEFAULT_PATH: &str = "bench.tsv";
+
+/// Summary of repeated measurements of a single stage.
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    pub median: Duration,
+    pub min: Duration,
+}
+
+/// Timings of the separate stages of a day. The parts are timed on an input
+/// that has already been parsed.
+pub struct Timings {
+    pub parse: Sample,
+    pub part1: Sample,
+    pub part2: Option<Sample>,
+}
+
+impl Timings {
+    pub fn stages(&self) -> impl Iterator<Item = (&'static str, Sample)> {
+        let stages = [
+            ("parse", Some(self.parse)),
+            ("part1", Some(self.part1)),
+            ("part2", self.part2),
+        ];
+        return stages
+            .into_iter()
+            .filter_map(|(name, sample)| sample.map(|s| (name, s)));
+    }
+}
+
+fn measure<T>(runs: usize, mut f: impl FnMut() -> T) -> Sample {
+    let mut durations: Vec<Duration> = (0..runs.max(1))
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .collect();
+    durations.sort();
+    return Sample {
+        median: durations[durations.len() / 2],
+        min: durations[0],
+    };
+}
+
+pub fn time<S: Solution>(s: &str, runs: usize) -> Result<Timings> {
+    let input = S::parse(s)?;
+    let parse = measure(runs, || S::parse(black_box(s)));
+    let part1 = measure(runs, || S::part1(black_box(&input)));
+    let part2 = S::part2(&input).map(|_| measure(runs, || S::part2(black_box(&input))));
+    return Ok(Timings {
+        parse,
+        part1,
+        part2,
+    });
+}
+
+/// Tab separated results, one line per stage, so that runs on different
+/// commits can be compared with `diff` or loaded into a spreadsheet.
+pub fn to_tsv<'a>(results: impl Iterator<Item = (u32, &'a Timings)>) -> String {
+    let mut tsv = String::from("day\tstage\tmedian_ns\tmin_ns\n");
+    for (day, timings) in results {
+        for (stage, sample) in timings.stages() {
+            tsv += &format!(
+                "{}\t{}\t{}\t{}\n",
+                day,
+                stage,
+                sample.median.as_nanos(),
+                sample.min.as_nanos()
+            );
+        }
+    }
+    return tsv;
+}
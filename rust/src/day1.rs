@@ -1,22 +1,23 @@
+use crate::parse::lines;
+use crate::{Result, Solution};
 use std::collections::HashMap;
-use std::env::args;
-use std::fs::File;
-use std::io;
-use std::io::BufRead;
-use std::path::Path;
-use std::process::exit;
 use std::vec::Vec;
 
-fn read(path: impl AsRef<Path>) -> io::Result<(Vec<i64>, Vec<i64>)> {
-    let file = File::open(path)?;
+fn parse(s: &str) -> Result<(Vec<i64>, Vec<i64>)> {
     let mut xs = Vec::new();
     let mut ys = Vec::new();
-    let reader = io::BufReader::new(file);
-    for line in reader.lines() {
-        let owned = line?;
-        let mut words = owned.split(" ").filter(|x| !x.is_empty());
-        let x = str::parse::<i64>(words.next().unwrap()).unwrap();
-        let y = str::parse::<i64>(words.next().unwrap()).unwrap();
+    for line in lines(s) {
+        let mut words = line.split(' ');
+        let mut next = || {
+            words
+                .next()
+                .ok_or_else(|| line.error("expected two numbers"))
+        };
+        let x = next()?.parse()?;
+        let y = next()?.parse()?;
+        if let Some(word) = words.next() {
+            return Err(word.error("unexpected token"));
+        }
         xs.push(x);
         ys.push(y);
     }
@@ -38,14 +39,22 @@ fn part2(xs: &[i64], ys: &[i64]) -> i64 {
     xs.iter().map(|x| counts.get(x).unwrap_or(&0) * x).sum()
 }
 
-pub fn main() {
-    match args().skip(1).next() {
-        None => exit(1),
-        Some(path) => {
-            let (mut xs, mut ys) = read(&path).unwrap();
-            println!("Part 1: {}", part1(&mut xs, &mut ys));
-            println!("Part 2: {}", part2(&xs, &ys));
-        }
+pub struct Day1;
+
+impl Solution for Day1 {
+    type Input = (Vec<i64>, Vec<i64>);
+    type Output = i64;
+
+    fn parse(s: &str) -> Result<Self::Input> {
+        parse(s)
+    }
+
+    fn part1((xs, ys): &Self::Input) -> Result<i64> {
+        Ok(part1(&mut xs.clone(), &mut ys.clone()))
+    }
+
+    fn part2((xs, ys): &Self::Input) -> Option<Result<i64>> {
+        Some(Ok(part2(xs, ys)))
     }
 }
 
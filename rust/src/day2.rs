@@ -1,22 +1,15 @@
+use crate::parse::{Line, lines};
+use crate::{Result, Solution};
 use itertools::Itertools;
-use std::env::args;
-use std::fs::File;
-use std::io;
-use std::io::BufRead;
-use std::process::exit;
 
 type Report = Vec<i64>;
 
-fn parse(s: &str) -> Report {
-    s.split(" ")
-        .filter(|x| !x.is_empty())
-        .map(|s| str::parse::<i64>(s).unwrap())
-        .collect()
+fn parse(line: Line) -> Result<Report> {
+    line.split(' ').map(|word| word.parse()).collect()
 }
 
-fn read(stream: impl io::Read) -> impl Iterator<Item = Report> {
-    let reader = io::BufReader::new(stream);
-    reader.lines().map(|res| parse(&res.unwrap()))
+fn read(s: &str) -> Result<Vec<Report>> {
+    lines(s).map(parse).collect()
 }
 
 fn has_strict_bounded_incs<'a>(it: impl Iterator<Item = &'a i64>) -> bool {
@@ -59,21 +52,29 @@ fn part2(reports: impl Iterator<Item = Report>) -> usize {
     reports.filter(is_nearly_safe).count()
 }
 
-pub fn main() {
-    match args().skip(1).next() {
-        None => exit(1),
-        Some(path) => {
-            let it = read(File::open(&path).unwrap());
-            println!("Part 1: {}", part1(it));
-            let it = read(File::open(&path).unwrap());
-            println!("Part 2: {}", part2(it));
-        }
+pub struct Day2;
+
+impl Solution for Day2 {
+    type Input = Vec<Report>;
+    type Output = usize;
+
+    fn parse(s: &str) -> Result<Self::Input> {
+        read(s)
+    }
+
+    fn part1(reports: &Self::Input) -> Result<usize> {
+        Ok(part1(reports.iter().cloned()))
+    }
+
+    fn part2(reports: &Self::Input) -> Option<Result<usize>> {
+        Some(Ok(part2(reports.iter().cloned())))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
     #[test]
     fn test_small_example_part1() {
@@ -90,7 +91,7 @@ mod tests {
 
     #[test]
     fn test_part1_on_input_data() {
-        let it = read(File::open("inputs/day2.txt").unwrap());
-        assert!(part1(it) == 390);
+        let reports = read(&fs::read_to_string("inputs/day2.txt").unwrap()).unwrap();
+        assert!(part1(reports.into_iter()) == 390);
     }
 }
@@ -1,14 +1,5 @@
+use crate::{Result, Solution};
 use regex::{Captures, Regex};
-use std::env::args;
-use std::fs::File;
-use std::io;
-use std::process::exit;
-
-fn read<S: io::Read>(stream: &mut S) -> String {
-    let mut contents = String::new();
-    stream.read_to_string(&mut contents).unwrap();
-    contents
-}
 
 #[derive(Debug)]
 enum Instruction {
@@ -70,21 +61,30 @@ fn part1(s: &str) -> i64 {
         .sum()
 }
 
-pub fn main() {
-    match args().skip(1).next() {
-        None => exit(1),
-        Some(path) => {
-            let mut file = File::open(&path).unwrap();
-            let contents = read(&mut file);
-            println!("Part1: {}", part1(&contents));
-            println!("Part2: {}", part2(&contents));
-        }
+pub struct Day3;
+
+impl Solution for Day3 {
+    type Input = String;
+    type Output = i64;
+
+    fn parse(s: &str) -> Result<Self::Input> {
+        Ok(s.to_string())
+    }
+
+    fn part1(s: &Self::Input) -> Result<i64> {
+        Ok(part1(s))
+    }
+
+    fn part2(s: &Self::Input) -> Option<Result<i64>> {
+        Some(Ok(part2(s)))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
+
     #[test]
     fn test_example_part1() {
         let s = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
@@ -99,15 +99,13 @@ mod tests {
 
     #[test]
     fn run_part1() {
-        let mut file = File::open("inputs/day3.txt").unwrap();
-        let s = read(&mut file);
+        let s = fs::read_to_string("inputs/day3.txt").unwrap();
         println!("{:?}", part1(&s));
     }
 
     #[test]
     fn run_part2() {
-        let mut file = File::open("inputs/day3.txt").unwrap();
-        let s = read(&mut file);
+        let s = fs::read_to_string("inputs/day3.txt").unwrap();
         println!("{:?}", part2(&s));
     }
 }
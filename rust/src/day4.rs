@@ -1,189 +1,458 @@
-use std::env::args;
-use std::fs::File;
-use std::io;
-use std::io::{BufRead, Error};
-use std::ops::Add;
+use crate::grid::{DIRECTIONS, Grid, Point};
+use crate::parse::InputError;
+use crate::registry::{ToolArgs, ToolResult};
+use crate::{Result, Solution};
+use rayon::prelude::*;
+use std::collections::VecDeque;
 
-use itertools::iproduct;
-use std::process::exit;
+pub struct Board(Grid<u8>);
 
-type Index = i32;
+impl Board {
+    fn parse(s: &str) -> Result<Board> {
+        // Letters are kept as bytes, so anything beyond ASCII is rejected.
+        let grid = Grid::parse(s, |_, c| match c {
+            _ if c.is_ascii() => Ok(c as u8),
+            _ => Err("expected an ASCII letter".to_string()),
+        })?;
+        return Ok(Board(grid));
+    }
 
-#[derive(Copy, Clone)]
-struct Point(Index, Index);
+    /// Panics unless the rows have the same width.
+    #[allow(dead_code)]
+    fn from(xs: &[&[u8]]) -> Board {
+        let grid = Grid::from_rows(xs.iter().map(|row| row.to_vec()));
+        return Board(grid.expect("rows of equal width"));
+    }
 
-impl Point {
-    fn translate(&mut self, dir: &Point) {
-        self.0 = self.0 + dir.0;
-        self.1 = self.1 + dir.1;
+    fn positions(&self) -> impl Iterator<Item = Point> + '_ {
+        return self.0.points();
     }
+}
+
+/// A list of words to search for together, as an Aho-Corasick automaton.
+/// The states are the prefixes of the words, and reading a letter moves to
+/// the longest prefix that ends the text read so far.
+pub struct Dictionary {
+    words: Vec<Vec<u8>>,
+    next: Vec<[u32; 256]>,
+    /// The words that end the prefix of each state.
+    ends: Vec<Vec<usize>>,
+}
 
-    fn origin(&self) -> bool {
-        return self.0 == 0 && self.1 == 0;
+impl Dictionary {
+    /// Empty words are never found.
+    pub fn new<W: AsRef<[u8]>>(words: &[W]) -> Dictionary {
+        let mut next = vec![[0; 256]];
+        let mut ends = vec![Vec::new()];
+        // The trie of the words first. No edge of it leads back to the root,
+        // so 0 marks a missing edge.
+        for (i, word) in words.iter().enumerate() {
+            let mut state = 0;
+            for &c in word.as_ref() {
+                if next[state][c as usize] == 0 {
+                    next[state][c as usize] = next.len() as u32;
+                    next.push([0; 256]);
+                    ends.push(Vec::new());
+                }
+                state = next[state][c as usize] as usize;
+            }
+            if state != 0 {
+                ends[state].push(i);
+            }
+        }
+        // Then the missing edges, which lead where the edge of the longest
+        // proper suffix does. Going breadth first, the states of the shorter
+        // suffixes are complete by the time they are needed.
+        let mut suffix = vec![0; next.len()];
+        let mut queue: VecDeque<usize> = next[0]
+            .iter()
+            .filter(|&&s| s != 0)
+            .map(|&s| s as usize)
+            .collect();
+        while let Some(state) = queue.pop_front() {
+            let fallback = suffix[state];
+            let inherited = ends[fallback].clone();
+            ends[state].extend(inherited);
+            let fallback_next = next[fallback];
+            for (edge, &fallback_edge) in next[state].iter_mut().zip(&fallback_next) {
+                if *edge != 0 {
+                    suffix[*edge as usize] = fallback_edge as usize;
+                    queue.push_back(*edge as usize);
+                } else {
+                    *edge = fallback_edge;
+                }
+            }
+        }
+        return Dictionary {
+            words: words.iter().map(|w| w.as_ref().to_vec()).collect(),
+            next,
+            ends,
+        };
+    }
+
+    /// Reads `text`, calling `found` with the index of every word found and
+    /// the position of its last letter in `text`.
+    fn scan(&self, text: impl Iterator<Item = u8>, mut found: impl FnMut(usize, usize)) {
+        let mut state = 0;
+        for (i, c) in text.enumerate() {
+            state = self.next[state][c as usize] as usize;
+            for &word in &self.ends[state] {
+                found(word, i);
+            }
+        }
     }
 }
 
-impl Add for Point {
-    type Output = Point;
+/// The first point of every line of the board that runs in direction `dir`.
+fn line_starts(board: &Board, dir: Point) -> impl Iterator<Item = Point> + '_ {
+    return board
+        .positions()
+        .filter(move |&p| !board.0.contains(p - dir));
+}
 
-    fn add(self, other: Self) -> Self {
-        Self(self.0 + other.0, self.1 + other.1)
+/// Reads every line of the board once per direction, calling `found` with
+/// every word of the dictionary found on it, its last point and its
+/// direction.
+fn scan_board(board: &Board, dictionary: &Dictionary, mut found: impl FnMut(usize, Point, Point)) {
+    for dir in DIRECTIONS {
+        for start in line_starts(board, dir) {
+            let line = board.0.ray(start, dir).map(|p| board.0[p]);
+            dictionary.scan(line, |word, i| found(word, start + dir * i as i32, dir));
+        }
     }
 }
 
-struct Cursor<'a> {
-    board: &'a Board,
-    current: Option<Point>,
+/// How often each word of the dictionary appears on the board, in any of
+/// the eight directions.
+pub fn count_words(board: &Board, dictionary: &Dictionary) -> Vec<usize> {
+    let mut counts = vec![0; dictionary.words.len()];
+    scan_board(board, dictionary, |word, _, _| counts[word] += 1);
+    return counts;
+}
+
+/// Counts the words on every line of the rows in `cells`, of the given
+/// width, both ways. Each line is copied into a contiguous buffer first, so
+/// that the automaton reads plain bytes instead of looking up points.
+fn count_in_rows(cells: &[u8], width: usize, dictionary: &Dictionary, counts: &mut [usize]) {
+    let height = cells.len() / width;
+    let mut count = |line: &[u8]| {
+        dictionary.scan(line.iter().copied(), |word, _| counts[word] += 1);
+        dictionary.scan(line.iter().rev().copied(), |word, _| counts[word] += 1);
+    };
+    let mut line = Vec::with_capacity(height.max(width));
+    for row in cells.chunks(width) {
+        count(row);
+    }
+    for column in 0..width {
+        line.clear();
+        line.extend((0..height).map(|row| cells[row * width + column]));
+        count(&line);
+    }
+    // The diagonals start on the top row or on the left (down to the right)
+    // or right (down to the left) column.
+    let starts = (0..width)
+        .map(|column| (0, column))
+        .chain((1..height).map(|row| (row, 0)));
+    for (row, column) in starts {
+        line.clear();
+        line.extend(
+            (0..(height - row).min(width - column)).map(|k| cells[(row + k) * width + column + k]),
+        );
+        count(&line);
+    }
+    let starts = (0..width)
+        .map(|column| (0, column))
+        .chain((1..height).map(|row| (row, width - 1)));
+    for (row, column) in starts {
+        line.clear();
+        line.extend(
+            (0..(height - row).min(column + 1)).map(|k| cells[(row + k) * width + column - k]),
+        );
+        count(&line);
+    }
 }
 
-impl<'a> Iterator for Cursor<'a> {
-    type Item = Point;
+/// Like `count_words`, for large boards. The board is cut into slabs of
+/// rows that are searched in parallel.
+pub fn count_words_parallel(board: &Board, dictionary: &Dictionary) -> Vec<usize> {
+    let slab = (board.0.height() / (4 * rayon::current_num_threads())).max(16);
+    return count_in_slabs(board, dictionary, slab);
+}
 
-    fn next(&mut self) -> Option<Point> {
-        if let Some(p) = self.current.take() {
-            if (p.1 as usize) < self.board.0[0].len() - 1 {
-                self.current = Some(Point(p.0, p.1 + 1));
-            } else if (p.0 as usize) < self.board.0.len() - 1 {
-                self.current = Some(Point(p.0 + 1, 0));
-            } else {
-                self.current = None;
+/// Each slab is extended by the rows that a word starting in it can reach,
+/// and the words within those extra rows alone are subtracted again, since
+/// the next slab counts them.
+fn count_in_slabs(board: &Board, dictionary: &Dictionary, slab: usize) -> Vec<usize> {
+    let (height, width) = (board.0.height(), board.0.width());
+    let words = dictionary.words.len();
+    if board.0.is_empty() {
+        return vec![0; words];
+    }
+    let reach = dictionary
+        .words
+        .iter()
+        .map(Vec::len)
+        .max()
+        .unwrap_or(1)
+        .max(1)
+        - 1;
+    let cells = board.0.cells();
+    return (0..height)
+        .into_par_iter()
+        .step_by(slab)
+        .map(|top| {
+            let bottom = (top + slab).min(height);
+            let end = (bottom + reach).min(height);
+            let mut counts = vec![0; words];
+            count_in_rows(
+                &cells[top * width..end * width],
+                width,
+                dictionary,
+                &mut counts,
+            );
+            let mut overlap = vec![0; words];
+            count_in_rows(
+                &cells[bottom * width..end * width],
+                width,
+                dictionary,
+                &mut overlap,
+            );
+            for (count, extra) in counts.iter_mut().zip(overlap) {
+                *count -= extra;
             }
-            return Some(p);
-        } else {
-            return None;
-        }
+            return counts;
+        })
+        .reduce(
+            || vec![0; words],
+            |mut a, b| {
+                for (x, y) in a.iter_mut().zip(b) {
+                    *x += y;
+                }
+                return a;
+            },
+        );
+}
+
+/// A word of a dictionary, by its index, read from `start` in direction
+/// `dir`.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Match {
+    pub start: Point,
+    pub dir: Point,
+    pub word: usize,
+}
+
+impl Match {
+    /// The points of the letters of the word.
+    pub fn points(&self, dictionary: &Dictionary) -> impl Iterator<Item = Point> {
+        let (start, dir) = (self.start, self.dir);
+        return (0..dictionary.words[self.word].len()).map(move |i| start + dir * i as i32);
     }
 }
 
-struct Board(Vec<Vec<u8>>);
+/// Every appearance of a word of the dictionary on the board, ordered by
+/// start point.
+pub fn find_words(board: &Board, dictionary: &Dictionary) -> Vec<Match> {
+    let mut matches = Vec::new();
+    scan_board(board, dictionary, |word, end, dir| {
+        let length = dictionary.words[word].len() as i32;
+        matches.push(Match {
+            start: end - dir * (length - 1),
+            dir,
+            word,
+        });
+    });
+    matches.sort_by_key(|m| (m.start, m.dir, m.word));
+    return matches;
+}
 
-impl Board {
-    fn read<S: io::Read>(stream: &mut S) -> Result<Board, Error> {
-        let mut contents: Vec<Vec<u8>> = Vec::new();
-        for result in io::BufReader::new(stream).lines() {
-            match result {
-                Err(error) => return Err(error),
-                Ok(line) => contents.push(Vec::from(line.as_bytes())),
-            }
-        }
-        return Ok(Board(contents));
+/// Draws the board with `.` in place of the letters that are not at one of
+/// the `points`, like the illustrations of the puzzle.
+pub fn highlight(board: &Board, points: impl IntoIterator<Item = Point>) -> String {
+    let mut picture = board.0.map(|_| '.');
+    for p in points {
+        picture[p] = char::from(board.0[p]);
     }
+    return picture.to_string();
+}
 
-    #[allow(dead_code)]
-    fn from(xs: &[&[u8]]) -> Board {
-        Board(xs.iter().map(|row| row.to_vec()).collect())
+fn part1(board: &Board, word: &[u8]) -> usize {
+    return count_words_parallel(board, &Dictionary::new(&[word]))[0];
+}
+
+/// A small grid of letters to look for on a board, where `.` stands for any
+/// letter.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Stencil(Grid<Option<u8>>);
+
+impl Stencil {
+    pub fn parse(s: &str) -> Result<Stencil> {
+        let grid = Grid::parse(s, |_, c| match c {
+            '.' => Ok(None),
+            _ if c.is_ascii() => Ok(Some(c as u8)),
+            _ => Err("expected an ASCII letter or `.`".to_string()),
+        })?;
+        if grid.is_empty() {
+            return Err(InputError::new(1, 1, "", "expected a pattern"));
+        }
+        return Ok(Stencil(grid));
     }
 
-    fn get(&self, p: &Point) -> Option<u8> {
-        if p.0 < 0 || p.1 < 0 {
-            return None;
+    /// The stencil turned a quarter clockwise.
+    fn rotate(&self) -> Stencil {
+        let height = self.0.height() as i32;
+        let mut rotated = Grid::new(self.0.width(), self.0.height(), None);
+        for (p, &cell) in self.0.iter() {
+            rotated[Point(p.1, height - 1 - p.0)] = cell;
         }
-        if self.0.len() <= (p.0 as usize) || self.0[0].len() <= (p.1 as usize) {
-            return None;
+        return Stencil(rotated);
+    }
+
+    /// The stencil mirrored left to right.
+    fn reflect(&self) -> Stencil {
+        let width = self.0.width() as i32;
+        let mut reflected = self.0.clone();
+        for (p, &cell) in self.0.iter() {
+            reflected[Point(p.0, width - 1 - p.1)] = cell;
         }
-        return Some(self.0[p.0 as usize][p.1 as usize]);
+        return Stencil(reflected);
     }
 
-    fn positions(&self) -> impl Iterator<Item = Point> {
-        return Cursor {
-            board: &self,
-            current: Some(Point(0, 0)),
-        };
+    fn fits_at(&self, board: &Board, top_left: Point) -> bool {
+        return self
+            .0
+            .iter()
+            .all(|(p, cell)| match (board.0.get(top_left + p), cell) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(letter), Some(c)) => letter == c,
+            });
     }
 }
 
-fn has_word(board: &Board, init: &Point, dir: &Point, word: &[u8]) -> bool {
-    let mut cursor: Point = *init;
-    for &c in word {
-        match board.get(&cursor) {
-            None => return false,
-            Some(b) => {
-                if c != b {
-                    return false;
+/// A stencil in all its distinct rotations and reflections.
+pub struct Pattern {
+    orientations: Vec<Stencil>,
+}
+
+impl Pattern {
+    pub fn new(stencil: &Stencil) -> Pattern {
+        let mut orientations: Vec<Stencil> = Vec::new();
+        let mut stencil = stencil.clone();
+        for _ in 0..4 {
+            for s in [stencil.clone(), stencil.reflect()] {
+                if !orientations.contains(&s) {
+                    orientations.push(s);
                 }
             }
+            stencil = stencil.rotate();
         }
-        cursor.translate(dir);
+        return Pattern { orientations };
     }
-    return true;
 }
 
-struct Ray {
-    current: Point,
-    dir: Point,
+/// Where the orientation of a pattern with the given index fits on a board.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Placement {
+    pub top_left: Point,
+    pub orientation: usize,
 }
 
-impl Ray {
-    fn new(initial: Point, dir: Point) -> Self {
-        Self {
-            current: initial,
-            dir: dir,
-        }
+impl Placement {
+    /// The points of the letters of the stencil, without its wildcards.
+    pub fn points<'a>(&self, pattern: &'a Pattern) -> impl Iterator<Item = Point> + 'a {
+        let top_left = self.top_left;
+        return pattern.orientations[self.orientation]
+            .0
+            .iter()
+            .filter(|(_, cell)| cell.is_some())
+            .map(move |(p, _)| top_left + p);
     }
 }
 
-impl Iterator for Ray {
-    type Item = Point;
-
-    fn next(&mut self) -> Option<Point> {
-        let result = self.current;
-        self.current = self.current + self.dir;
-        Some(result)
+/// Every placement of the pattern on the board, in row-major order of the
+/// top left corner.
+pub fn find_pattern(board: &Board, pattern: &Pattern) -> Vec<Placement> {
+    let mut placements = Vec::new();
+    for top_left in board.positions() {
+        for (orientation, stencil) in pattern.orientations.iter().enumerate() {
+            if stencil.fits_at(board, top_left) {
+                placements.push(Placement {
+                    top_left,
+                    orientation,
+                });
+            }
+        }
     }
+    return placements;
 }
 
-fn has_xmas(board: &Board, point: &Point) -> bool {
-    let w1: Vec<u8> = Ray::new(*point + Point(-1, -1), Point(1, 1))
-        .map_while(|p| board.get(&p))
-        .take(3)
-        .collect();
-    let w2: Vec<u8> = Ray::new(*point + Point(1, -1), Point(-1, 1))
-        .map_while(|p| board.get(&p))
-        .take(3)
-        .collect();
-    return (w1 == "MAS".as_bytes() || w1 == "SAM".as_bytes())
-        && (w2 == "MAS".as_bytes() || w2 == "SAM".as_bytes());
+/// Two `MAS` crossing diagonally, in any orientation.
+const X_MAS: &str = "M.S\n.A.\nM.S\n";
+
+fn part2(board: &Board) -> usize {
+    let stencil = Stencil::parse(X_MAS).expect("a valid stencil");
+    return find_pattern(board, &Pattern::new(&stencil)).len();
 }
 
-fn part1(board: &Board, word: &[u8]) -> usize {
-    let mut total = 0;
-    let dirs: Vec<Point> = iproduct!([-1, 0, 1], [-1, 0, 1])
-        .map(|(i, j)| Point(i, j))
-        .filter(|p| !p.origin())
-        .collect();
-    for p in board.positions() {
-        for dir in dirs.iter() {
-            if has_word(&board, &p, dir, word) {
-                total += 1;
-            }
-        }
+/// Prints how often each of the words given as `--words=XMAS,MAS` appears,
+/// and with `--highlight` the board with only the letters of those words.
+pub fn search(s: &str, args: &ToolArgs) -> ToolResult {
+    let Some(list) = args.value::<String>("words") else {
+        return Err("expected a list of words like `--words=XMAS,MAS`".into());
+    };
+    let words: Vec<&str> = list.split(',').collect();
+    let board = Board::parse(s)?;
+    let dictionary = Dictionary::new(&words);
+    if args.flag("highlight") {
+        let matches = find_words(&board, &dictionary);
+        print!(
+            "{}",
+            highlight(&board, matches.iter().flat_map(|m| m.points(&dictionary)))
+        );
+    }
+    let counts = count_words(&board, &dictionary);
+    for (word, count) in words.iter().zip(counts) {
+        println!("{}: {}", word, count);
     }
-    return total;
+    return Ok(());
 }
 
-fn part2(board: &Board) -> usize {
-    let mut total = 0;
-    for p in board.positions() {
-        if board.get(&p) == Some(b'A') && has_xmas(&board, &p) {
-            total += 1;
-        }
+/// Prints how often the stencil given as `--pattern=M.S/.A./M.S`, with rows
+/// separated by `/`, appears in any rotation or reflection, and with
+/// `--highlight` the board with only the letters where it does.
+pub fn stencil(s: &str, args: &ToolArgs) -> ToolResult {
+    let Some(rows) = args.value::<String>("pattern") else {
+        return Err("expected a pattern like `--pattern=M.S/.A./M.S`".into());
+    };
+    let stencil = Stencil::parse(&rows.replace('/', "\n"))?;
+    let board = Board::parse(s)?;
+    let pattern = Pattern::new(&stencil);
+    let placements = find_pattern(&board, &pattern);
+    if args.flag("highlight") {
+        let points = placements.iter().flat_map(|p| p.points(&pattern));
+        print!("{}", highlight(&board, points));
     }
-    return total;
+    println!("{}", placements.len());
+    return Ok(());
 }
 
-fn main() {
-    match args().nth(1) {
-        None => {
-            println!("Specify an input file");
-            exit(1);
-        }
-        Some(path) => {
-            let mut file = File::open(&path).unwrap();
-            if let Ok(board) = Board::read(&mut file) {
-                println!("Part1: {}", part1(&board, "XMAS".as_bytes()));
-                println!("Part2: {}", part2(&board));
-            }
-        }
+pub struct Day4;
+
+impl Solution for Day4 {
+    type Input = Board;
+    type Output = usize;
+
+    fn parse(s: &str) -> Result<Board> {
+        return Board::parse(s);
+    }
+
+    fn part1(board: &Board) -> Result<usize> {
+        return Ok(part1(board, "XMAS".as_bytes()));
+    }
+
+    fn part2(board: &Board) -> Option<Result<usize>> {
+        return Some(Ok(part2(board)));
     }
 }
 
@@ -191,6 +460,7 @@ fn main() {
 mod tests {
     use super::*;
     use lazy_static::lazy_static;
+    use std::fs;
 
     lazy_static! {
         static ref BOARD: Board = Board::from(&[
@@ -217,17 +487,205 @@ mod tests {
         assert_eq!(part2(&BOARD), 9);
     }
 
+    /// Checks every start and direction, one word at a time.
+    fn count_naively(board: &Board, word: &[u8]) -> usize {
+        let mut total = 0;
+        for p in board.positions() {
+            for dir in DIRECTIONS {
+                let letters: Vec<u8> = board
+                    .0
+                    .ray(p, dir)
+                    .take(word.len())
+                    .map(|q| board.0[q])
+                    .collect();
+                if letters == word {
+                    total += 1;
+                }
+            }
+        }
+        return total;
+    }
+
+    #[test]
+    fn dictionary_counts_every_word_in_one_pass() {
+        let words: [&[u8]; 7] = [
+            b"XMAS",
+            b"SAMX",
+            b"MAS",
+            b"AS",
+            b"A",
+            b"MMMSXXMASM",
+            b"XMAS",
+        ];
+        let counts = count_words(&BOARD, &Dictionary::new(&words));
+        let expected: Vec<usize> = words.iter().map(|w| count_naively(&BOARD, w)).collect();
+        assert_eq!(counts, expected);
+        assert_eq!(counts[..2], [18, 18]);
+        assert_eq!(counts[5], 1);
+        assert_eq!(count_words(&BOARD, &Dictionary::new(&["", "Q"])), [0, 0]);
+    }
+
+    #[test]
+    fn matches_are_located_and_highlighted() {
+        let dictionary = Dictionary::new(&["XMAS"]);
+        let matches = find_words(&BOARD, &dictionary);
+        assert_eq!(matches.len(), 18);
+        assert_eq!(
+            matches[0],
+            Match {
+                start: Point(0, 4),
+                dir: Point(1, 1),
+                word: 0
+            }
+        );
+        let letters: Vec<u8> = matches[0].points(&dictionary).map(|p| BOARD.0[p]).collect();
+        assert_eq!(letters, b"XMAS");
+        assert_eq!(
+            highlight(&BOARD, matches.iter().flat_map(|m| m.points(&dictionary))),
+            "....XXMAS.\n\
+             .SAMXMS...\n\
+             ...S..A...\n\
+             ..A.A.MS.X\n\
+             XMASAMX.MM\n\
+             X.....XA.A\n\
+             S.S.S.S.SS\n\
+             .A.A.A.A.A\n\
+             ..M.M.M.MM\n\
+             .X.X.XMASX\n"
+        );
+    }
+
+    #[test]
+    fn stencils_come_in_every_orientation() {
+        let stencil = |s: &str| Stencil::parse(s).unwrap();
+        assert_eq!(Pattern::new(&stencil(X_MAS)).orientations.len(), 4);
+        let corner = Pattern::new(&stencil("XM\nA.\n"));
+        assert_eq!(corner.orientations.len(), 8);
+        assert!(corner.orientations.contains(&stencil("AX\n.M\n")));
+        assert_eq!(Pattern::new(&stencil("XMAS")).orientations.len(), 4);
+        assert_eq!(Pattern::new(&stencil("A")).orientations.len(), 1);
+        assert!(Stencil::parse("").is_err());
+        assert!(Stencil::parse("M.\nS\n").is_err());
+    }
+
+    #[test]
+    fn patterns_are_located() {
+        let pattern = Pattern::new(&Stencil::parse(X_MAS).unwrap());
+        let placements = find_pattern(&BOARD, &pattern);
+        assert_eq!(placements.len(), 9);
+        assert_eq!(
+            placements[0],
+            Placement {
+                top_left: Point(0, 1),
+                orientation: 0,
+            }
+        );
+        assert_eq!(
+            highlight(&BOARD, placements.iter().flat_map(|p| p.points(&pattern))),
+            ".M.S......\n\
+             ..A..MSMS.\n\
+             .M.S.MAA..\n\
+             ..A.ASMSM.\n\
+             .M.S.M....\n\
+             ..........\n\
+             S.S.S.S.S.\n\
+             .A.A.A.A..\n\
+             M.M.M.M.M.\n\
+             ..........\n"
+        );
+        // A word is a pattern of one row, which only turns to lie along rows
+        // and columns.
+        let xmas = Pattern::new(&Stencil::parse("XMAS").unwrap());
+        let orthogonal = find_words(&BOARD, &Dictionary::new(&["XMAS"]))
+            .iter()
+            .filter(|m| m.dir.0 == 0 || m.dir.1 == 0)
+            .count();
+        assert_eq!(find_pattern(&BOARD, &xmas).len(), orthogonal);
+    }
+
+    #[test]
+    fn slabs_count_like_rays() {
+        let words = [
+            "XMAS",
+            "SAMX",
+            "MAS",
+            "A",
+            "MMMSXXMASM",
+            "XMASAMXAMMMAM",
+            "AMXS",
+        ];
+        let dictionary = Dictionary::new(&words);
+        let expected = count_words(&BOARD, &dictionary);
+        for slab in 1..=11 {
+            assert_eq!(
+                count_in_slabs(&BOARD, &dictionary, slab),
+                expected,
+                "{}",
+                slab
+            );
+        }
+        let board = Board::parse(&fs::read_to_string("inputs/day4.txt").unwrap()).unwrap();
+        let expected = count_words(&board, &dictionary);
+        assert_eq!(count_words_parallel(&board, &dictionary), expected);
+        assert_eq!(count_in_slabs(&board, &dictionary, 3), expected);
+    }
+
+    #[test]
+    fn empty_boards_have_no_matches() {
+        for s in ["", "\n", "\n\n"] {
+            let board = Board::parse(s).unwrap();
+            assert_eq!(board.positions().count(), 0);
+            assert_eq!(part1(&board, b"XMAS"), 0);
+            assert_eq!(part2(&board), 0);
+            assert_eq!(highlight(&board, []), "");
+        }
+    }
+
+    #[test]
+    fn single_rows_and_columns() {
+        let row = Board::parse("XMASAMX\n").unwrap();
+        assert_eq!(row.positions().count(), 7);
+        assert_eq!(part1(&row, b"XMAS"), 2);
+        assert_eq!(part2(&row), 0);
+        let column = Board::parse("X\nM\nA\nS\n").unwrap();
+        assert_eq!(part1(&column, b"XMAS"), 1);
+        let matches = find_words(&column, &Dictionary::new(&["XMAS"]));
+        assert_eq!(
+            matches,
+            vec![Match {
+                start: Point(0, 0),
+                dir: Point(1, 0),
+                word: 0
+            }]
+        );
+        // A word of one letter is read once in every direction.
+        let cell = Board::parse("X").unwrap();
+        assert_eq!(count_words(&cell, &Dictionary::new(&["X"])), [8]);
+        assert_eq!(highlight(&cell, [Point(0, 0)]), "X\n");
+    }
+
+    #[test]
+    fn ragged_boards_are_rejected() {
+        let err = Board::parse("XMAS\nXM\nXMAS\n").err().unwrap();
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (2, "expected a row of 4 cells, found 2")
+        );
+        let err = Board::parse("XMAS\n\nXMAS\n").err().unwrap();
+        assert_eq!(err.line, 2);
+        let err = Board::parse("XM\u{c4}S\n").err().unwrap();
+        assert_eq!((err.line, err.column), (1, 3));
+    }
+
     #[test]
     fn test_part1_on_input_data() {
-        let mut file = File::open("inputs/day4.txt").unwrap();
-        let board = Board::read(&mut file).unwrap();
+        let board = Board::parse(&fs::read_to_string("inputs/day4.txt").unwrap()).unwrap();
         assert_eq!(part1(&board, b"XMAS"), 2336);
     }
 
     #[test]
     fn test_part2_on_input_data() {
-        let mut file = File::open("inputs/day4.txt").unwrap();
-        let board = Board::read(&mut file).unwrap();
+        let board = Board::parse(&fs::read_to_string("inputs/day4.txt").unwrap()).unwrap();
         assert_eq!(part2(&board), 1831);
     }
 }
@@ -1,36 +1,59 @@
+use crate::parse::{Line, lines};
+use crate::registry::{ToolArgs, ToolResult};
+use crate::{InputError, Result, Solution};
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
-use std::env::args;
-use std::fs::File;
+use std::fmt;
 use std::hash::Hash;
-use std::io;
-use std::io::BufRead;
-use std::io::Lines;
-use std::process::exit;
 
 type Rule = (u32, u32);
 type Update = Vec<u32>;
 type Index = HashMap<u32, HashSet<u32>>;
 
-fn read_updates<B: BufRead>(lines: &mut Lines<B>) -> impl Iterator<Item = Update> {
-    fn parse_update(s: &str) -> Update {
-        s.split(",").map(|s| s.parse().unwrap()).collect()
+/// Reads the updates together with the lines they are on.
+fn read_updates<'a>(lines: impl Iterator<Item = Line<'a>>) -> Result<Vec<(usize, Update)>> {
+    fn parse_update(line: Line) -> Result<(usize, Update)> {
+        let update: Update = line.split(',').map(|s| s.parse()).collect::<Result<_>>()?;
+        if update.is_empty() {
+            return Err(line.error("expected a comma separated list of pages"));
+        }
+        return Ok((line.number, update));
     }
 
-    lines.map(|res| parse_update(&res.unwrap()))
+    lines.map(parse_update).collect()
 }
 
-fn read_rules<B: BufRead>(lines: &mut Lines<B>) -> impl Iterator<Item = Rule> {
-    fn parse_rule(s: &str) -> Option<Rule> {
-        let words: Vec<&str> = s.split("|").collect();
-        if words.len() == 2 {
-            return Some((str::parse(words[0]).unwrap(), str::parse(words[1]).unwrap()));
-        } else {
-            return None;
-        }
+/// Reads rules up to the blank line that separates them from the updates.
+fn read_rules<'a>(lines: impl Iterator<Item = Line<'a>>) -> Result<Vec<Rule>> {
+    fn parse_rule(line: Line) -> Result<Rule> {
+        let (x, y) = line.split_once('|')?;
+        return Ok((x.parse()?, y.parse()?));
     }
 
-    lines.map_while(|res| parse_rule(&res.unwrap()))
+    lines
+        .take_while(|line| !line.text.is_empty())
+        .map(parse_rule)
+        .collect()
+}
+
+pub struct Manual {
+    index: Index,
+    updates: Vec<Update>,
+    /// The line of the input each update is on.
+    lines: Vec<usize>,
+}
+
+impl Manual {
+    fn parse(s: &str) -> Result<Manual> {
+        let mut lines = lines(s);
+        let index = create_index(read_rules(lines.by_ref())?.into_iter());
+        let (lines, updates) = read_updates(lines)?.into_iter().unzip();
+        return Ok(Manual {
+            index,
+            updates,
+            lines,
+        });
+    }
 }
 
 fn create_index(rules: impl Iterator<Item = Rule>) -> Index {
@@ -44,7 +67,7 @@ fn create_index(rules: impl Iterator<Item = Rule>) -> Index {
             .or_insert(HashSet::from([x]));
         // Ensure that minimal elements also appear as keys in the
         // hash set. This is important for Kahn's algorithm to work.
-        index.entry(x).or_insert(HashSet::new());
+        index.entry(x).or_default();
     }
     return index;
 }
@@ -61,11 +84,35 @@ fn is_correct(index: &Index, update: &Update) -> bool {
     return true;
 }
 
-fn part1<B: BufRead>(lines: &mut Lines<B>) -> u32 {
-    let index: Index = create_index(read_rules(lines));
+/// A rule `x|y` that an update breaks, with the positions of `x` and `y` in
+/// the update, the latter being the smaller.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Violation {
+    pub rule: Rule,
+    pub positions: (usize, usize),
+}
+
+/// Every rule that the update breaks, ordered by the positions of the pages.
+fn violations(index: &Index, update: &Update) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for (j, y) in update.iter().enumerate() {
+        for (i, x) in update.iter().enumerate().skip(j + 1) {
+            if index.get(y).is_some_and(|lt| lt.contains(x)) {
+                violations.push(Violation {
+                    rule: (*x, *y),
+                    positions: (i, j),
+                });
+            }
+        }
+    }
+    violations.sort_by_key(|v| v.positions);
+    return violations;
+}
+
+fn part1(manual: &Manual) -> u32 {
     let mut total = 0;
-    for update in read_updates(lines) {
-        if is_correct(&index, &update) {
+    for update in &manual.updates {
+        if is_correct(&manual.index, update) {
             total += update[update.len() / 2];
         }
     }
@@ -98,12 +145,12 @@ fn reverse(index: &Index) -> Index {
                 })
                 .or_insert(HashSet::from([*x]));
         }
-        reversed.entry(*x).or_insert(HashSet::new());
+        reversed.entry(*x).or_default();
     }
     return reversed;
 }
 
-fn restrict_index(index: &Index, v: &Vec<u32>) -> Index {
+fn restrict_index(index: &Index, v: &[u32]) -> Index {
     let universe: HashSet<u32> = v.iter().copied().collect();
     let mut result: Index = HashMap::new();
     for (x, lt) in index {
@@ -112,10 +159,63 @@ fn restrict_index(index: &Index, v: &Vec<u32>) -> Index {
             result.insert(*x, lt_);
         }
     }
+    // Pages without any rules can go anywhere, but still need a place.
+    for x in v {
+        result.entry(*x).or_default();
+    }
     return result;
 }
 
-fn topological_sort(mut index: Index) -> Vec<u32> {
+/// Pages that the rules require to be in a circle, each before the next and
+/// the last before the first, so that they cannot be ordered.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Cycle(pub Vec<u32>);
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let next = self.0.iter().cycle().skip(1);
+        let rules: Vec<String> = self
+            .0
+            .iter()
+            .zip(next)
+            .map(|(x, y)| format!("{}|{}", x, y))
+            .collect();
+        return write!(f, "{}", rules.join(", "));
+    }
+}
+
+/// Follows the rules backwards from the smallest page that Kahn's algorithm
+/// could not place. Every such page must come after another one of them, so
+/// the walk ends up going around a cycle.
+fn find_cycle(index: &Index) -> Cycle {
+    let before = |x: &u32| index[x].iter().min().copied();
+    let mut page = index
+        .iter()
+        .filter(|(_, lt)| !lt.is_empty())
+        .map(|(x, _)| *x)
+        .min();
+    let mut path = Vec::new();
+    while let Some(x) = page {
+        if let Some(start) = path.iter().position(|&y| y == x) {
+            let mut cycle = path.split_off(start);
+            cycle.reverse();
+            let first = cycle
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, y)| y)
+                .map(|(i, _)| i);
+            cycle.rotate_left(first.unwrap_or(0));
+            return Cycle(cycle);
+        }
+        path.push(x);
+        page = before(&x);
+    }
+    unreachable!("pages that cannot be placed have a page before them");
+}
+
+/// Orders the pages of `index` by Kahn's algorithm, or finds a cycle that
+/// prevents any order.
+fn topological_sort(mut index: Index) -> std::result::Result<Vec<u32>, Cycle> {
     let mut pending: HashSet<u32> = HashSet::new();
     let reversed = reverse(&index);
     for (x, lt) in index.iter() {
@@ -139,7 +239,10 @@ fn topological_sort(mut index: Index) -> Vec<u32> {
             }
         }
     }
-    return order;
+    if order.len() < index.len() {
+        return Err(find_cycle(&index));
+    }
+    return Ok(order);
 }
 
 fn from_vec(order: Vec<u32>) -> impl Fn(&u32, &u32) -> Ordering {
@@ -148,52 +251,287 @@ fn from_vec(order: Vec<u32>) -> impl Fn(&u32, &u32) -> Ordering {
         iso.insert(x, i);
     }
 
-    return move |x, y| iso.get(x).unwrap().cmp(&iso.get(y).unwrap());
+    return move |x, y| iso.get(x).unwrap().cmp(iso.get(y).unwrap());
+}
+
+fn sort(index: &Index, update: &Update) -> std::result::Result<Update, Cycle> {
+    // The unrestricted relation represented by `index` contains cycles, and hence
+    // Kahn's algorithm fails. In particular this means the relation is not a poset.
+    //
+    // The relation restricted to the pages of an update usually has no
+    // cycles. Then topological sort gives a linear refinement of it, and
+    // otherwise the update cannot be put in order at all.
+    let cmp = from_vec(topological_sort(restrict_index(index, update))?);
+    let mut update = update.clone();
+    update.sort_by(&cmp);
+    return Ok(update);
 }
 
-fn from_index(index: Index) -> impl Fn(&u32, &u32) -> Ordering {
-    return from_vec(topological_sort(index));
+/// A page taken out of an update at position `from` and put back so that it
+/// ends up at position `to`.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Move {
+    pub page: u32,
+    pub from: usize,
+    pub to: usize,
 }
 
-fn part2<B: BufRead>(lines: &mut Lines<B>) -> u32 {
-    let index: Index = create_index(read_rules(lines));
+/// Finds a right partner for the left position `i` in a maximum matching,
+/// moving other positions to new partners if need be.
+fn augment(
+    i: usize,
+    edge: &dyn Fn(usize, usize) -> bool,
+    seen: &mut [bool],
+    partner: &mut [Option<usize>],
+) -> bool {
+    for j in 0..partner.len() {
+        if edge(i, j) && !seen[j] {
+            seen[j] = true;
+            if partner[j].is_none_or(|k| augment(k, edge, seen, partner)) {
+                partner[j] = Some(i);
+                return true;
+            }
+        }
+    }
+    return false;
+}
+
+/// Reorders the update to follow the rules by moving as few pages as
+/// possible, and returns the new order with the moves that lead to it.
+///
+/// A set of pages can stay where they are if no rule, directly or through
+/// other pages, puts one of them before another that precedes it. Such pages
+/// form an antichain of the order of broken rules between positions, and
+/// the largest one is found from a maximum matching as in König's theorem.
+/// Pages whose rules form a cycle cannot be put in order at all.
+fn repair(index: &Index, update: &Update) -> std::result::Result<(Update, Vec<Move>), Cycle> {
+    let n = update.len();
+    let restricted = restrict_index(index, update);
+    topological_sort(restricted.clone())?;
+    // `before[i][j]` if the page at `i` must come before the page at `j`.
+    let mut before = vec![vec![false; n]; n];
+    for (j, y) in update.iter().enumerate() {
+        for (i, x) in update.iter().enumerate() {
+            before[i][j] = restricted[y].contains(x);
+        }
+    }
+    for k in 0..n {
+        for i in 0..n {
+            for j in 0..n {
+                before[i][j] |= before[i][k] && before[k][j];
+            }
+        }
+    }
+    let breaks = |i: usize, j: usize| i < j && before[j][i];
+    let mut partner = vec![None; n];
+    for i in 0..n {
+        augment(i, &breaks, &mut vec![false; n], &mut partner);
+    }
+    // The positions reachable by alternating paths from unmatched positions
+    // on the left, on the left and right side.
+    let mut left = vec![false; n];
+    let mut right = vec![false; n];
+    let mut queue: Vec<usize> = (0..n).filter(|i| !partner.contains(&Some(*i))).collect();
+    while let Some(i) = queue.pop() {
+        if left[i] {
+            continue;
+        }
+        left[i] = true;
+        for j in 0..n {
+            if breaks(i, j) && partner[j] != Some(i) && !right[j] {
+                right[j] = true;
+                queue.extend(partner[j]);
+            }
+        }
+    }
+    let stays: Vec<bool> = (0..n).map(|i| left[i] && !right[i]).collect();
+
+    // The new order puts the earliest possible position next, keeping the
+    // pages that stay in their order.
+    let mut placed = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    for _ in 0..n {
+        let next = (0..n)
+            .find(|&j| {
+                !placed[j]
+                    && (0..n).all(|i| placed[i] || !before[i][j])
+                    && (!stays[j] || (0..j).all(|i| placed[i] || !stays[i]))
+            })
+            .expect("pages without cycles can be ordered");
+        placed[next] = true;
+        order.push(next);
+    }
+
+    // Every page that moves is put right after the page before it in the new
+    // order that is already in place.
+    let mut current: Vec<usize> = (0..n).collect();
+    let mut settled = stays.clone();
+    let mut moves = Vec::new();
+    for (k, &j) in order.iter().enumerate() {
+        if settled[j] {
+            continue;
+        }
+        settled[j] = true;
+        let from = current.iter().position(|&i| i == j).unwrap();
+        current.remove(from);
+        let to = match order[..k].iter().rev().find(|&&i| settled[i]) {
+            Some(&i) => current.iter().position(|&x| x == i).unwrap() + 1,
+            None => 0,
+        };
+        current.insert(to, j);
+        if from != to {
+            moves.push(Move {
+                page: update[j],
+                from,
+                to,
+            });
+        }
+    }
+    return Ok((order.into_iter().map(|i| update[i]).collect(), moves));
+}
+
+/// The number of pages that have to move to turn `from` into `to`, which is
+/// the number of pages not on a longest common subsequence.
+fn moves_between(from: &Update, to: &Update) -> usize {
+    let n = from.len();
+    let mut longest = vec![vec![0; n + 1]; n + 1];
+    for i in 0..n {
+        for j in 0..n {
+            longest[i + 1][j + 1] = if from[i] == to[j] {
+                longest[i][j] + 1
+            } else {
+                longest[i][j + 1].max(longest[i + 1][j])
+            };
+        }
+    }
+    return n - longest[n][n];
+}
+
+/// The sum of the middle pages of the incorrect updates, once put in order
+/// by `fix`. Fails at the first of them whose rules form a cycle.
+fn total_of_fixed(
+    manual: &Manual,
+    fix: impl Fn(&Index, &Update) -> std::result::Result<Update, Cycle>,
+) -> Result<u32> {
+    let index = &manual.index;
     let mut total = 0;
-    for mut update in read_updates(lines) {
-        if !is_correct(&index, &update) {
-            // The unrestricted relation represented by `index` contains cycles, and hence
-            // Kahn's algorithm fails. In particular this means the relation is not a poset.
-            //
-            // The restricted relations yield usable comparison functions.
-            // Hence, topological sort gives a linear refinement of the
-            // restricted relation. The fact that a linear refinement exists, means that
-            // the restricted relation was a poset.
-            let cmp = from_index(restrict_index(&index, &update));
-            update.sort_by(&cmp);
-            total += update[update.len() / 2];
+    for (update, &line) in manual.updates.iter().zip(&manual.lines) {
+        if is_correct(index, update) {
+            continue;
         }
+        let update = fix(index, update).map_err(|cycle| {
+            let pages: Vec<String> = update.iter().map(u32::to_string).collect();
+            let message = format!("the rules for these pages form a cycle: {}", cycle);
+            return InputError::new(line, 1, &pages.join(","), message);
+        })?;
+        total += update[update.len() / 2];
     }
-    return total;
+    return Ok(total);
 }
 
-fn main() {
-    match args().nth(1) {
-        None => exit(1),
-        Some(path) => {
-            let mut lines = io::BufReader::new(File::open(&path).unwrap()).lines();
-            println!("Part1: {}", part1(&mut lines));
+fn part2(manual: &Manual) -> Result<u32> {
+    return total_of_fixed(manual, sort);
+}
+
+/// Prints every update with the rules it breaks and where the pages of those
+/// rules are, counting positions from 0. Updates whose rules form a cycle are
+/// explained as well.
+pub fn explain(s: &str, _args: &ToolArgs) -> ToolResult {
+    let manual = Manual::parse(s)?;
+    for update in &manual.updates {
+        let pages: Vec<String> = update.iter().map(u32::to_string).collect();
+        let violations = violations(&manual.index, update);
+        if violations.is_empty() {
+            println!("{}: correct", pages.join(","));
+            continue;
+        }
+        let rules = if violations.len() == 1 {
+            "rule"
+        } else {
+            "rules"
+        };
+        println!("{}: breaks {} {}", pages.join(","), violations.len(), rules);
+        for Violation { rule, positions } in violations {
+            println!(
+                "  {}|{}: {} at {}, {} at {}",
+                rule.0, rule.1, rule.0, positions.0, rule.1, positions.1
+            );
+        }
+        if let Err(cycle) = topological_sort(restrict_index(&manual.index, update)) {
+            println!("  the rules for these pages form a cycle: {}", cycle);
+        }
+    }
+    return Ok(());
+}
 
-            let mut lines = io::BufReader::new(File::open(&path).unwrap()).lines();
-            println!("Part2: {}", part2(&mut lines));
+/// Prints every incorrect update put in order both by sorting and by moving
+/// as few pages as possible, with the moves of the latter.
+pub fn repairs(s: &str, _args: &ToolArgs) -> ToolResult {
+    let manual = Manual::parse(s)?;
+    let join = |update: &Update| -> String {
+        let pages: Vec<String> = update.iter().map(u32::to_string).collect();
+        return pages.join(",");
+    };
+    let moves_of = |n: usize| match n {
+        1 => "1 move".to_string(),
+        _ => format!("{} moves", n),
+    };
+    for update in &manual.updates {
+        if is_correct(&manual.index, update) {
+            continue;
+        }
+        println!("{}", join(update));
+        let (sorted, (repaired, moves)) =
+            match (sort(&manual.index, update), repair(&manual.index, update)) {
+                (Ok(sorted), Ok(repaired)) => (sorted, repaired),
+                (Err(cycle), _) | (_, Err(cycle)) => {
+                    println!("  the rules for these pages form a cycle: {}", cycle);
+                    continue;
+                }
+            };
+        println!(
+            "  sorted:   {} ({})",
+            join(&sorted),
+            moves_of(moves_between(update, &sorted))
+        );
+        println!(
+            "  repaired: {} ({})",
+            join(&repaired),
+            moves_of(moves.len())
+        );
+        for Move { page, from, to } in moves {
+            println!("    move {} from {} to {}", page, from, to);
         }
     }
+    return Ok(());
+}
+
+pub struct Day5;
+
+impl Solution for Day5 {
+    type Input = Manual;
+    type Output = u32;
+
+    fn parse(s: &str) -> Result<Manual> {
+        return Manual::parse(s);
+    }
+
+    fn part1(manual: &Manual) -> Result<u32> {
+        return Ok(part1(manual));
+    }
+
+    fn part2(manual: &Manual) -> Option<Result<u32>> {
+        return Some(part2(manual));
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
     fn from_rules(rules: impl Iterator<Item = Rule>) -> impl Fn(&u32, &u32) -> Ordering {
-        return from_vec(topological_sort(create_index(rules)));
+        return from_vec(topological_sort(create_index(rules)).unwrap());
     }
 
     #[test]
@@ -229,17 +567,164 @@ mod tests {
         assert_eq!(v, vec![75, 97, 47, 29, 13]);
     }
 
+    #[test]
+    fn cycles_are_found() {
+        let rules = [(4, 1), (1, 2), (2, 3), (3, 1), (3, 5)];
+        let cycle = topological_sort(create_index(rules.into_iter())).unwrap_err();
+        assert_eq!(cycle, Cycle(vec![1, 2, 3]));
+        assert_eq!(cycle.to_string(), "1|2, 2|3, 3|1");
+        let cycle = topological_sort(create_index([(7, 7)].into_iter())).unwrap_err();
+        assert_eq!(cycle.to_string(), "7|7");
+    }
+
+    #[test]
+    fn updates_with_cyclic_rules_are_rejected() {
+        let manual = Manual::parse("1|2\n2|3\n3|1\n\n1,2\n3,2,1\n").unwrap();
+        assert_eq!(part1(&manual), 2);
+        let err = part2(&manual).err().unwrap();
+        assert_eq!((err.line, err.token.as_str()), (6, "3,2,1"));
+        assert_eq!(
+            err.message,
+            "the rules for these pages form a cycle: 1|2, 2|3, 3|1"
+        );
+        // Cycles among the rules only matter for updates that contain them.
+        let manual = Manual::parse("1|2\n2|3\n3|1\n\n2,1\n").unwrap();
+        assert_eq!(part2(&manual), Ok(2));
+    }
+
+    #[test]
+    fn pages_without_rules_are_sorted_too() {
+        let index = create_index([(1, 2)].into_iter());
+        let update = vec![2, 1, 9];
+        let mut sorted = sort(&index, &update).unwrap();
+        assert!(is_correct(&index, &sorted), "{:?}", sorted);
+        sorted.sort();
+        assert_eq!(sorted, vec![1, 2, 9]);
+    }
+
+    #[test]
+    fn violations_name_both_pages() {
+        let manual = Manual::parse(&fs::read_to_string("inputs/day5-small.txt").unwrap()).unwrap();
+        let rules = |update: &Update| -> Vec<(Rule, (usize, usize))> {
+            return violations(&manual.index, update)
+                .into_iter()
+                .map(|v| (v.rule, v.positions))
+                .collect();
+        };
+        assert_eq!(rules(&manual.updates[0]), vec![]);
+        assert_eq!(rules(&manual.updates[3]), vec![((97, 75), (1, 0))]);
+        assert_eq!(
+            rules(&manual.updates[5]),
+            vec![
+                ((75, 13), (2, 1)),
+                ((29, 13), (3, 1)),
+                ((47, 13), (4, 1)),
+                ((47, 29), (4, 3))
+            ]
+        );
+        for update in &manual.updates {
+            assert_eq!(
+                violations(&manual.index, update).is_empty(),
+                is_correct(&manual.index, update)
+            );
+        }
+    }
+
+    /// Applies the moves one after the other.
+    fn apply(update: &Update, moves: &[Move]) -> Update {
+        let mut update = update.clone();
+        for m in moves {
+            assert_eq!(update.remove(m.from), m.page);
+            update.insert(m.to, m.page);
+        }
+        return update;
+    }
+
+    /// The fewest moves over every order of the update that breaks no rule.
+    fn fewest_moves(index: &Index, update: &Update) -> usize {
+        fn orders(pages: &[u32]) -> Vec<Update> {
+            if pages.is_empty() {
+                return vec![vec![]];
+            }
+            let mut result = Vec::new();
+            for i in 0..pages.len() {
+                let mut rest = pages.to_vec();
+                let page = rest.remove(i);
+                for mut order in orders(&rest) {
+                    order.insert(0, page);
+                    result.push(order);
+                }
+            }
+            return result;
+        }
+        return orders(update)
+            .iter()
+            .filter(|order| is_correct(index, order))
+            .map(|order| moves_between(update, order))
+            .min()
+            .unwrap();
+    }
+
+    #[test]
+    fn repairs_move_as_few_pages_as_possible() {
+        let manual = Manual::parse(&fs::read_to_string("inputs/day5-small.txt").unwrap()).unwrap();
+        let (repaired, moves) = repair(&manual.index, &manual.updates[3]).unwrap();
+        assert_eq!(repaired, vec![97, 75, 47, 61, 53]);
+        assert_eq!(
+            moves,
+            vec![Move {
+                page: 75,
+                from: 0,
+                to: 1
+            }]
+        );
+        let (repaired, moves) = repair(&manual.index, &manual.updates[5]).unwrap();
+        assert_eq!(repaired, vec![97, 75, 47, 29, 13]);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(total_of_fixed(&manual, |i, u| Ok(repair(i, u)?.0)), Ok(123));
+
+        // Rules that leave the order open, where sorting may move more pages
+        // than needed.
+        let rules = [(1, 4), (2, 4), (5, 3), (6, 2), (6, 1), (3, 4), (5, 6)];
+        let index = create_index(rules.into_iter());
+        let updates = [
+            vec![4, 1, 2, 3, 5, 6],
+            vec![4, 3, 2, 1, 6, 5],
+            vec![1, 2, 3, 4, 5, 6],
+            vec![6, 4, 5, 1, 7, 2],
+            vec![2, 1, 4, 6, 3],
+        ];
+        for update in updates {
+            let (repaired, moves) = repair(&index, &update).unwrap();
+            assert!(is_correct(&index, &repaired), "{:?}", repaired);
+            assert_eq!(apply(&update, &moves), repaired);
+            let fewest = fewest_moves(&index, &update);
+            assert_eq!(moves.len(), fewest, "{:?}", update);
+            // Sorting finds some order that breaks no rule, which may or may
+            // not be one of those with the fewest moves.
+            let sorted = sort(&index, &update).unwrap();
+            assert!(moves_between(&update, &sorted) >= fewest, "{:?}", sorted);
+        }
+    }
+
+    #[test]
+    fn repairing_solves_part2() {
+        let manual = Manual::parse(&fs::read_to_string("inputs/day5.txt").unwrap()).unwrap();
+        assert_eq!(
+            total_of_fixed(&manual, |i, u| Ok(repair(i, u)?.0)),
+            Ok(4077)
+        );
+    }
+
     #[test]
     fn part1_on_small_example() {
-        let path = "inputs/day5-small.txt";
-        let mut lines = io::BufReader::new(File::open(&path).unwrap()).lines();
-        assert_eq!(part1(&mut lines), 143);
+        let manual = Manual::parse(&fs::read_to_string("inputs/day5-small.txt").unwrap()).unwrap();
+        assert_eq!(part1(&manual), 143);
     }
 
     #[test]
     fn part2_on_small_example() {
-        let path = "inputs/day5-small.txt";
-        let mut lines = io::BufReader::new(File::open(&path).unwrap()).lines();
-        assert_eq!(part2(&mut lines), 123);
+        let manual = Manual::parse(&fs::read_to_string("inputs/day5-small.txt").unwrap()).unwrap();
+        assert_eq!(part2(&manual), Ok(123));
     }
 }
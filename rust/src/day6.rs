@@ -1,16 +1,19 @@
+use crate::grid::{self, Grid, Point};
+use crate::parse::InputError;
+use crate::registry::{ToolArgs, ToolResult};
+use crate::{Result, Solution};
 use rayon::prelude::*;
-use std::collections::HashSet;
-use std::env::args;
-use std::fs::File;
-use std::io;
-use std::io::BufRead;
-use std::process::exit;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
 
 #[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
-struct Point(usize, usize);
-
-#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
-enum Dir {
+pub enum Dir {
     Up,
     Right,
     Down,
@@ -23,33 +26,16 @@ impl Dir {
     const VALUES: [Dir; 4] = [Up, Right, Down, Left];
 
     fn parse(c: char) -> Option<Dir> {
-        for dir in Dir::VALUES {
-            if c == char::from(&dir) {
-                return Some(dir);
-            }
-        }
-        return None;
+        return Dir::VALUES.into_iter().find(|&dir| c == char::from(&dir));
     }
 
-    fn step(&self, p: &Point) -> Option<Point> {
-        match self {
-            Up => {
-                if p.0 > 0 {
-                    return Some(Point(p.0 - 1, p.1));
-                } else {
-                    return None;
-                }
-            }
-            Right => return Some(Point(p.0, p.1 + 1)),
-            Down => return Some(Point(p.0 + 1, p.1)),
-            Left => {
-                if p.1 > 0 {
-                    return Some(Point(p.0, p.1 - 1));
-                } else {
-                    return None;
-                }
-            }
-        }
+    /// The offset of a step.
+    fn delta(&self) -> Point {
+        return grid::ORTHOGONAL[self.index()];
+    }
+
+    fn index(&self) -> usize {
+        return *self as usize;
     }
 
     fn turn(&self) -> Dir {
@@ -68,34 +54,176 @@ impl From<&Dir> for char {
             Up => return '^',
             Right => return '>',
             Left => return '<',
-            Down => return 'V',
+            Down => return 'v',
         }
     }
 }
 
-impl ToString for Dir {
-    fn to_string(&self) -> String {
-        return char::from(self).into();
+impl fmt::Display for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", char::from(self));
     }
 }
 
 #[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
-struct Guard {
-    pos: Point,
-    dir: Dir,
+pub struct Guard {
+    pub pos: Point,
+    pub dir: Dir,
+}
+
+/// Which way the guard turns when it runs into an obstacle.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Turn {
+    Right,
+    Left,
+    Around,
+}
+
+/// How the heading of the guard translates into a step.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Movement {
+    Orthogonal,
+    /// Every heading is rotated 45 degrees clockwise, so that a guard facing
+    /// up moves up and to the right.
+    Diagonal,
+}
+
+/// What a step of a guard that runs into an obstacle is spent on.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Collision {
+    /// Turning is a step of its own, in which the guard stays where it is.
+    TurnInPlace,
+    /// The guard turns and moves on in the same step, so that every step
+    /// leads to a new cell.
+    TurnAndStep,
+}
+
+impl FromStr for Turn {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Turn, String> {
+        match s {
+            "right" => Ok(Turn::Right),
+            "left" => Ok(Turn::Left),
+            "around" => Ok(Turn::Around),
+            _ => Err(format!(
+                "unknown turn `{}`, expected right, left or around",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for Movement {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Movement, String> {
+        match s {
+            "orthogonal" => Ok(Movement::Orthogonal),
+            "diagonal" => Ok(Movement::Diagonal),
+            _ => Err(format!(
+                "unknown movement `{}`, expected orthogonal or diagonal",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for Collision {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Collision, String> {
+        match s {
+            "turn" => Ok(Collision::TurnInPlace),
+            "turn-and-step" => Ok(Collision::TurnAndStep),
+            _ => Err(format!(
+                "unknown collision `{}`, expected turn or turn-and-step",
+                s
+            )),
+        }
+    }
+}
+
+/// How guards move, so that variants of the puzzle can share the simulation.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Rules {
+    pub turn: Turn,
+    pub movement: Movement,
+    pub collision: Collision,
 }
 
-impl Guard {
-    fn turn(&mut self) {
-        self.dir = self.dir.turn();
+impl Rules {
+    pub const PUZZLE: Rules = Rules {
+        turn: Turn::Right,
+        movement: Movement::Orthogonal,
+        collision: Collision::TurnAndStep,
+    };
+
+    /// The puzzle rules, with any of `--turn`, `--movement` and `--collision`
+    /// given to a tool replacing the corresponding rule.
+    fn from_args(args: &ToolArgs) -> std::result::Result<Rules, String> {
+        let mut rules = Rules::PUZZLE;
+        if let Some(turn) = args.value::<String>("turn") {
+            rules.turn = turn.parse()?;
+        }
+        if let Some(movement) = args.value::<String>("movement") {
+            rules.movement = movement.parse()?;
+        }
+        if let Some(collision) = args.value::<String>("collision") {
+            rules.collision = collision.parse()?;
+        }
+        return Ok(rules);
+    }
+
+    fn turn(&self, dir: Dir) -> Dir {
+        match self.turn {
+            Turn::Right => dir.turn(),
+            Turn::Left => dir.turn().turn().turn(),
+            Turn::Around => dir.turn().turn(),
+        }
+    }
+
+    fn delta(&self, dir: Dir) -> Point {
+        let d = dir.delta();
+        match self.movement {
+            Movement::Orthogonal => d,
+            Movement::Diagonal => Point(d.0 + d.1, d.1 - d.0),
+        }
+    }
+}
+
+/// What happens to a guard that walks off an edge of the board.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Topology {
+    /// The guard leaves, as in the puzzle.
+    Exit,
+    /// The guard comes back in at the opposite edge.
+    Torus,
+    /// The edges are walls, which the guard turns at like at an obstacle.
+    Walls,
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Topology, String> {
+        match s {
+            "exit" => Ok(Topology::Exit),
+            "torus" => Ok(Topology::Torus),
+            "walls" => Ok(Topology::Walls),
+            _ => Err(format!(
+                "unknown topology `{}`, expected exit, torus or walls",
+                s
+            )),
+        }
     }
 }
 
 #[derive(Clone)]
 struct Board {
-    height: usize,
-    width: usize,
-    obstacles: HashSet<Point>,
+    obstacles: Grid<bool>,
+    rules: Rules,
+    topology: Topology,
 }
 
 struct WalkState<'a> {
@@ -108,132 +236,877 @@ impl Iterator for WalkState<'_> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let guard = self.guard;
-        self.board.step(&mut self.guard);
+        self.guard = guard.and_then(|g| self.board.step(g));
         return guard;
     }
 }
 
 impl Board {
-    fn step(&self, guard: &mut Option<Guard>) {
-        if let Some(g) = guard {
-            if let Some(p) = g.dir.step(&g.pos) {
-                if !self.contains(&p) {
-                    guard.take();
-                } else {
-                    if self.obstacles.contains(&p) {
-                        g.turn();
-                        self.step(guard);
-                    } else {
-                        g.pos = p;
-                    }
-                }
-            } else {
-                guard.take();
+    /// The state of the guard after one step, or `None` once it has left the
+    /// board.
+    fn step(&self, guard: Guard) -> Option<Guard> {
+        return self.step_with(guard, |p| self.is_obstacle(p));
+    }
+
+    /// Like `step`, with `blocked` deciding which cells the guard cannot
+    /// enter.
+    fn step_with(&self, mut guard: Guard, blocked: impl Fn(&Point) -> bool) -> Option<Guard> {
+        // A guard that is boxed in has tried every direction after four
+        // turns, and then stays where it is.
+        for _ in 0..4 {
+            match self.offset(&guard.pos, self.rules.delta(guard.dir)) {
+                Some(p) if !blocked(&p) => return Some(Guard { pos: p, ..guard }),
+                None if self.topology == Topology::Exit => return None,
+                _ => {}
+            }
+            guard.dir = self.rules.turn(guard.dir);
+            if self.rules.collision == Collision::TurnInPlace {
+                break;
             }
         }
+        return Some(guard);
+    }
+
+    /// The point `delta` away from `p`, if it is on the board. On a torus
+    /// every point is.
+    fn offset(&self, p: &Point, delta: Point) -> Option<Point> {
+        let q = *p + delta;
+        if self.topology == Topology::Torus {
+            let height = self.obstacles.height() as i32;
+            let width = self.obstacles.width() as i32;
+            return Some(Point(q.0.rem_euclid(height), q.1.rem_euclid(width)));
+        }
+        return self.contains(&q).then_some(q);
     }
 
     fn contains(&self, p: &Point) -> bool {
-        return p.0 < self.height && p.1 < self.width;
+        return self.obstacles.contains(*p);
+    }
+
+    fn is_obstacle(&self, p: &Point) -> bool {
+        return self.obstacles[*p];
+    }
+
+    fn with_obstacle(&self, p: &Point) -> Board {
+        let mut board = self.clone();
+        board.obstacles[*p] = true;
+        return board;
     }
 
     fn walk(&self, guard: Option<Guard>) -> impl Iterator<Item = Guard> {
-        return WalkState {
-            board: self,
-            guard: guard,
-        };
+        return WalkState { board: self, guard };
+    }
+
+    /// Steps all guards at once. Guards cannot enter a cell that another
+    /// guard stays on, swap places with another guard, or move into the same
+    /// cell as another guard, and treat such cells as obstacles. Guards that
+    /// left the board stay `None`.
+    fn tick(&self, guards: &[Option<Guard>]) -> Vec<Option<Guard>> {
+        // Barring a cell can only keep more guards where they are, so the
+        // barred cells grow until no guard is in another's way.
+        let mut barred = HashSet::new();
+        let mut swaps = HashSet::new();
+        loop {
+            let next: Vec<Option<Guard>> = guards
+                .iter()
+                .map(|g| {
+                    return g.and_then(|g| {
+                        let blocked = |p: &Point| {
+                            self.is_obstacle(p)
+                                || barred.contains(p)
+                                || swaps.contains(&(g.pos, *p))
+                        };
+                        return self.step_with(g, blocked);
+                    });
+                })
+                .collect();
+            let mut arrivals: HashMap<Point, usize> = HashMap::new();
+            let mut moves = HashSet::new();
+            let mut stays = HashSet::new();
+            for (g, n) in guards.iter().zip(&next) {
+                match (g, n) {
+                    (Some(g), Some(n)) if g.pos != n.pos => {
+                        *arrivals.entry(n.pos).or_default() += 1;
+                        moves.insert((g.pos, n.pos));
+                    }
+                    (Some(g), Some(_)) => {
+                        stays.insert(g.pos);
+                    }
+                    _ => {}
+                }
+            }
+            let conflicts: Vec<Point> = arrivals
+                .into_iter()
+                .filter(|&(p, count)| count > 1 || stays.contains(&p))
+                .map(|(p, _)| p)
+                .collect();
+            let head_on: Vec<(Point, Point)> = moves
+                .iter()
+                .filter(|&&(from, to)| moves.contains(&(to, from)))
+                .copied()
+                .collect();
+            if conflicts.is_empty() && head_on.is_empty() {
+                return next;
+            }
+            barred.extend(conflicts);
+            swaps.extend(head_on);
+        }
+    }
+
+    /// The states of all guards after every tick, until they have all left
+    /// the board.
+    fn walk_all(&self, guards: &[Guard]) -> impl Iterator<Item = Vec<Option<Guard>>> {
+        let on_board = |guards: &Vec<Option<Guard>>| guards.iter().any(Option::is_some);
+        let start: Vec<Option<Guard>> = guards.iter().copied().map(Some).collect();
+        return std::iter::successors(Some(start).filter(on_board), move |guards| {
+            return Some(self.tick(guards)).filter(on_board);
+        });
     }
 }
 
-struct Instance {
+pub struct Instance {
     board: Board,
-    guard: Option<Guard>,
+    guards: Vec<Guard>,
 }
 
 impl Instance {
-    fn read<S: io::Read>(stream: &mut S) -> Result<Self, io::Error> {
-        let mut obstacles = HashSet::new();
-        let mut height = 0;
-        let mut width = 0;
-        let mut guard = None;
-        for (i, res) in io::BufReader::new(stream).lines().enumerate() {
-            let line = res?;
-            for (j, c) in line.chars().enumerate() {
-                match c {
-                    '.' => continue,
-                    '#' => {
-                        obstacles.insert(Point(i, j));
-                    }
-                    _ => {
-                        if let Some(dir) = Dir::parse(c) {
-                            guard.replace(Guard {
-                                pos: Point(i, j),
-                                dir: dir,
-                            });
-                        };
-                    }
-                }
+    fn parse(s: &str) -> Result<Instance> {
+        return Instance::parse_with(s, &[]);
+    }
+
+    /// Parses the map, accepting the `aliases` as guard glyphs besides
+    /// `^`, `>`, `v` and `<`.
+    fn parse_with(s: &str, aliases: &[(char, Dir)]) -> Result<Instance> {
+        let mut guards = Vec::new();
+        let obstacles = Grid::parse(s, |pos, c| {
+            let guard = Dir::parse(c).or_else(|| {
+                aliases
+                    .iter()
+                    .find(|&&(alias, _)| alias == c)
+                    .map(|&(_, dir)| dir)
+            });
+            match (c, guard) {
+                ('.' | '#', _) => {}
+                (_, Some(dir)) => guards.push(Guard { pos, dir }),
+                (_, None) => return Err("unknown character".to_string()),
             }
-            height += 1;
-            width = line.len();
+            return Ok(c == '#');
+        })?;
+        if guards.is_empty() {
+            return Err(InputError::new(1, 1, "", "expected at least one guard"));
         }
         return Ok(Instance {
             board: Board {
-                height: height,
-                width: width,
-                obstacles: obstacles,
+                obstacles,
+                rules: Rules::PUZZLE,
+                topology: Topology::Exit,
             },
-            guard: guard,
+            guards,
         });
     }
 }
 
-fn part1(path: &str) -> usize {
-    let mut file = File::open(&path).unwrap();
-    let instance = Instance::read(&mut file).unwrap();
-    let mut visited = HashSet::new();
-    for guard in instance.board.walk(instance.guard) {
-        visited.insert(guard.pos);
+/// Marks the cells of the board that any guard visits.
+fn visited(instance: &Instance) -> Grid<bool> {
+    let board = &instance.board;
+    let mut visited = board.obstacles.map(|_| false);
+    for guards in until_repeat(board.walk_all(&instance.guards)) {
+        for guard in guards.iter().flatten() {
+            visited[guard.pos] = true;
+        }
     }
-    return visited.len();
+    return visited;
+}
+
+fn part1(instance: &Instance) -> usize {
+    return visited(instance).iter().filter(|&(_, &v)| v).count();
+}
+
+/// Ends a walk once it repeats a state, since from there on it loops. Guards
+/// that cannot leave the board always end up looping.
+fn until_repeat<T: Clone + Eq + Hash>(walk: impl Iterator<Item = T>) -> impl Iterator<Item = T> {
+    let mut seen = HashSet::new();
+    return walk.take_while(move |state| seen.insert(state.clone()));
+}
+
+/// For every cell and direction, the cell where a guard walking straight
+/// ahead stops in front of an obstacle, or `None` if it leaves the board.
+/// Lines on a torus have no ends to start from, so it is not supported.
+struct Jumps {
+    stops: Grid<[Option<Point>; 4]>,
 }
 
-fn pred(board: &mut Board, point: Point, guard: Option<Guard>) -> bool {
-    board.obstacles.insert(point);
-    let mut history = HashSet::new();
-    for guard in board.walk(guard) {
-        if history.contains(&guard) {
-            return true;
+impl Jumps {
+    fn new(board: &Board) -> Jumps {
+        let mut stops = board.obstacles.map(|_| [None; 4]);
+        for dir in Dir::VALUES {
+            // The stop of a cell is that of the cell ahead, so the cells
+            // ahead must be filled in first.
+            let delta = board.rules.delta(dir);
+            // Points compare in row-major order.
+            let order: Vec<Point> = if delta < Point(0, 0) {
+                stops.points().collect()
+            } else {
+                stops.points().rev().collect()
+            };
+            for p in order {
+                stops[p][dir.index()] = match board.offset(&p, delta) {
+                    Some(ahead) if board.is_obstacle(&ahead) => Some(p),
+                    Some(ahead) => stops[ahead][dir.index()],
+                    None if board.topology == Topology::Walls => Some(p),
+                    None => None,
+                };
+            }
         }
-        history.insert(guard);
+        return Jumps { stops };
     }
-    return false;
-}
 
-fn part2(path: &str) -> usize {
-    let mut file = File::open(&path).unwrap();
-    let instance = Instance::read(&mut file).unwrap();
+    /// Where `guard` stops when an extra obstacle is placed at `obstacle`.
+    fn stop(&self, board: &Board, guard: &Guard, obstacle: &Point) -> Option<Point> {
+        let stop = self.stops[guard.pos][guard.dir.index()];
+        let pos = guard.pos;
+        // The extra obstacle only matters if it is `k` steps straight ahead.
+        let d = board.rules.delta(guard.dir);
+        let diff = *obstacle - pos;
+        let k = if d.0 != 0 { diff.0 / d.0 } else { diff.1 / d.1 };
+        if k <= 0 || diff != d * k {
+            return stop;
+        }
+        let blocked = pos + d * (k - 1);
+        match stop {
+            Some(stop) if stop.chebyshev(pos) < blocked.chebyshev(pos) => Some(stop),
+            _ => Some(blocked),
+        }
+    }
 
-    let mut visited = HashSet::new();
-    for guard in instance.board.walk(instance.guard) {
-        visited.insert(guard.pos);
+    /// Whether `guard` walks in a loop once an obstacle is placed at
+    /// `obstacle`. Only the states where the guard turns are recorded, since
+    /// a loop must repeat one of them.
+    fn loops(&self, board: &Board, obstacle: &Point, mut guard: Guard) -> bool {
+        let mut turns = board.obstacles.map(|_| 0u8);
+        while let Some(pos) = self.stop(board, &guard, obstacle) {
+            guard = Guard {
+                pos,
+                dir: board.rules.turn(guard.dir),
+            };
+            let seen = &mut turns[pos];
+            let bit = 1 << guard.dir.index();
+            if *seen & bit != 0 {
+                return true;
+            }
+            *seen |= bit;
+        }
+        return false;
     }
+}
 
+/// An obstacle can only change the patrol if it is placed on the original
+/// path. The guard then walks the same way until it first bumps into it, so
+/// each candidate comes with the state just before that.
+fn candidates(board: &Board, start: Guard) -> Vec<(Point, Guard)> {
+    let mut seen = board.obstacles.map(|_| false);
+    let mut candidates = Vec::new();
+    let mut walk = until_repeat(board.walk(Some(start))).peekable();
+    while let (Some(prev), Some(&next)) = (walk.next(), walk.peek()) {
+        let moved = next.pos != prev.pos;
+        if moved && next.pos != start.pos && !seen[next.pos] {
+            seen[next.pos] = true;
+            let guard = Guard {
+                pos: prev.pos,
+                dir: next.dir,
+            };
+            candidates.push((next.pos, guard));
+        }
+    }
+    return candidates;
+}
+
+/// The obstructions that trap a lone guard, found with the jump tables.
+fn traps(board: &Board, start: Guard) -> Vec<Point> {
+    let jumps = Jumps::new(board);
+    return candidates(board, start)
+        .par_iter()
+        .filter(|(obstacle, guard)| jumps.loops(board, obstacle, *guard))
+        .map(|(obstacle, _)| *obstacle)
+        .collect();
+}
+
+/// The cells on the path of some guard, other than where the guards start.
+/// Guards interact, so obstacles elsewhere could still matter in principle,
+/// but only by way of a guard that bumps into them.
+fn obstructions(instance: &Instance) -> Vec<Point> {
+    let mut visited = visited(instance);
+    for guard in &instance.guards {
+        visited[guard.pos] = false;
+    }
     return visited
+        .iter()
+        .filter(|&(_, &v)| v)
+        .map(|(p, _)| p)
+        .collect();
+}
+
+fn part2(instance: &Instance) -> usize {
+    match instance.guards.as_slice() {
+        [guard] if instance.board.topology != Topology::Torus => {
+            traps(&instance.board, *guard).len()
+        }
+        _ => obstructions(instance)
+            .par_iter()
+            .filter(|obstruction| trace(instance, **obstruction).is_some())
+            .count(),
+    }
+}
+
+/// An obstruction that traps at least one guard.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Loop {
+    pub obstruction: Point,
+    /// The number of steps before the guards are back in the same state.
+    pub length: usize,
+    /// The states of the trapped guards when the loop begins.
+    pub entry: Vec<Guard>,
+}
+
+/// Walks from the start with the obstruction in place, step by step, to
+/// find where the loop begins and how long it is.
+fn trace(instance: &Instance, obstruction: Point) -> Option<Loop> {
+    let board = instance.board.with_obstacle(&obstruction);
+    let mut seen = HashMap::new();
+    for (n, guards) in board.walk_all(&instance.guards).enumerate() {
+        if let Some(&k) = seen.get(&guards) {
+            return Some(Loop {
+                obstruction,
+                length: n - k,
+                entry: guards.into_iter().flatten().collect(),
+            });
+        }
+        seen.insert(guards, n);
+    }
+    return None;
+}
+
+/// The map with the aliases of `--aliases=V:v,A:^`, and the rules and
+/// `--topology` given to a tool.
+fn read(s: &str, args: &ToolArgs) -> std::result::Result<Instance, Box<dyn Error>> {
+    let mut aliases = Vec::new();
+    if let Some(list) = args.value::<String>("aliases") {
+        for alias in list.split(',') {
+            let mut chars = alias.chars();
+            match (
+                chars.next(),
+                chars.next(),
+                chars.next().and_then(Dir::parse),
+                chars.next(),
+            ) {
+                (Some(c), Some(':'), Some(dir), None) => aliases.push((c, dir)),
+                _ => return Err(format!("expected an alias like `V:v`, got `{}`", alias).into()),
+            }
+        }
+    }
+    let mut instance = Instance::parse_with(s, &aliases)?;
+    instance.board.rules = Rules::from_args(args)?;
+    if let Some(topology) = args.value::<String>("topology") {
+        instance.board.topology = topology.parse()?;
+    }
+    return Ok(instance);
+}
+
+/// Every obstruction that traps a guard, in row-major order. Guards that
+/// cannot leave the board walk in a loop anyway, so there every obstruction
+/// on their path counts, with the loop they end up in.
+pub fn loops(instance: &Instance) -> Vec<Loop> {
+    let obstructions = match instance.guards.as_slice() {
+        [guard] if instance.board.topology != Topology::Torus => traps(&instance.board, *guard),
+        _ => obstructions(instance),
+    };
+    let mut loops: Vec<Loop> = obstructions
         .par_iter()
-        .filter(|p| pred(&mut instance.board.clone(), **p, instance.guard))
-        .count();
+        .filter_map(|obstruction| trace(instance, *obstruction))
+        .collect();
+    loops.sort_by_key(|l| l.obstruction);
+    return loops;
+}
+
+/// Prints every obstruction that traps a guard, with the length of the loop
+/// and the states in which the trapped guards enter it.
+pub fn list_loops(s: &str, args: &ToolArgs) -> ToolResult {
+    let instance = read(s, args)?;
+    for l in loops(&instance) {
+        let entry: Vec<String> = l
+            .entry
+            .iter()
+            .map(|g| format!("{},{} facing {}", g.pos.0, g.pos.1, g.dir))
+            .collect();
+        println!(
+            "{},{}: loop of {} steps, entered at {}",
+            l.obstruction.0,
+            l.obstruction.1,
+            l.length,
+            entry.join(" and ")
+        );
+    }
+    return Ok(());
+}
+
+/// The cells a guard has walked through, marked with the lines it moved
+/// along.
+struct Trail {
+    marks: Grid<u8>,
+}
+
+impl Trail {
+    const VERTICAL: u8 = 1;
+    const HORIZONTAL: u8 = 2;
+    const RISING: u8 = 4;
+    const FALLING: u8 = 8;
+
+    fn new(board: &Board) -> Trail {
+        return Trail {
+            marks: board.obstacles.map(|_| 0),
+        };
+    }
+
+    fn mark(&mut self, board: &Board, pos: &Point, dir: Dir) {
+        self.marks[*pos] |= match board.rules.delta(dir) {
+            Point(_, 0) => Trail::VERTICAL,
+            Point(0, _) => Trail::HORIZONTAL,
+            Point(di, dj) if di != dj => Trail::RISING,
+            _ => Trail::FALLING,
+        };
+    }
 }
 
-fn main() {
-    match args().nth(1) {
-        None => {
-            println!("Specify an input file");
-            exit(1);
+/// Walks the guards until they have left the board or repeat a state,
+/// calling `visit` after every step. Returns the trail and whether the guards
+/// ended up walking in a loop.
+fn patrol(
+    board: &Board,
+    guards: &[Guard],
+    mut visit: impl FnMut(&Trail, &[Guard]),
+) -> (Trail, bool) {
+    let mut trail = Trail::new(board);
+    let mut seen = HashSet::new();
+    let mut prev: Option<Vec<Option<Guard>>> = None;
+    for guards in board.walk_all(guards) {
+        if !seen.insert(guards.clone()) {
+            return (trail, true);
+        }
+        for (i, guard) in guards.iter().enumerate() {
+            let Some(guard) = guard else {
+                continue;
+            };
+            // The guard may have turned before stepping, so the cell it left
+            // is also marked with the direction it left in.
+            if let Some(Some(prev)) = prev.as_ref().map(|p| p[i]) {
+                trail.mark(board, &prev.pos, guard.dir);
+            }
+            trail.mark(board, &guard.pos, guard.dir);
         }
-        Some(path) => {
-            println!("Part 1: {}", part1(&path));
-            println!("Part 2: {}", part2(&path))
+        let active: Vec<Guard> = guards.iter().flatten().copied().collect();
+        visit(&trail, &active);
+        prev = Some(guards);
+    }
+    return (trail, false);
+}
+
+/// Draws the board, with `|`, `-`, `/` and `\\` for cells that were walked
+/// through along one line, `+` for several, and `O` for the added obstruction.
+fn render(board: &Board, trail: &Trail, guards: &[Guard], obstruction: Option<&Point>) -> String {
+    let mut picture = trail.marks.map(|&mark| match mark {
+        0 => '.',
+        Trail::VERTICAL => '|',
+        Trail::HORIZONTAL => '-',
+        Trail::RISING => '/',
+        Trail::FALLING => '\\',
+        _ => '+',
+    });
+    for (p, &obstacle) in board.obstacles.iter() {
+        if obstacle {
+            picture[p] = if obstruction == Some(&p) { 'O' } else { '#' };
         }
     }
+    for guard in guards {
+        picture[guard.pos] = char::from(&guard.dir);
+    }
+    return picture.to_string();
+}
+
+/// The board with the obstruction given by `--obstruction=row,col`, if any.
+fn obstructed(
+    instance: &Instance,
+    args: &ToolArgs,
+) -> std::result::Result<(Board, Option<Point>), String> {
+    let obstruction = match args.value::<String>("obstruction") {
+        None => return Ok((instance.board.clone(), None)),
+        Some(s) => s.parse::<Point>()?,
+    };
+    if !instance.board.contains(&obstruction) {
+        return Err(format!(
+            "obstruction {:?} is outside the board",
+            obstruction
+        ));
+    }
+    if instance.guards.iter().any(|g| g.pos == obstruction) {
+        return Err("the obstruction cannot be placed on a guard".to_string());
+    }
+    return Ok((
+        instance.board.with_obstacle(&obstruction),
+        Some(obstruction),
+    ));
+}
+
+/// Prints the board with the paths of the guards. `--obstruction=row,col` adds
+/// an obstruction first, as in part 2.
+pub fn draw(s: &str, args: &ToolArgs) -> ToolResult {
+    let instance = read(s, args)?;
+    let (board, obstruction) = obstructed(&instance, args)?;
+    let (trail, looped) = patrol(&board, &instance.guards, |_, _| {});
+    print!("{}", render(&board, &trail, &[], obstruction.as_ref()));
+    if looped {
+        println!("The guards walk in a loop.");
+    }
+    return Ok(());
+}
+
+/// Redraws the board in the terminal after every step of the guards, waiting
+/// `--delay=ms` in between.
+pub fn animate(s: &str, args: &ToolArgs) -> ToolResult {
+    let instance = read(s, args)?;
+    let (board, obstruction) = obstructed(&instance, args)?;
+    let delay = Duration::from_millis(args.value("delay").unwrap_or(50));
+    let (_, looped) = patrol(&board, &instance.guards, |trail, guards| {
+        // Clear the screen and move the cursor to the top left.
+        print!("\x1b[2J\x1b[H");
+        print!("{}", render(&board, trail, guards, obstruction.as_ref()));
+        let _ = io::stdout().flush();
+        sleep(delay);
+    });
+    if looped {
+        println!("The guards walk in a loop.");
+    }
+    return Ok(());
+}
+
+pub struct Day6;
+
+impl Solution for Day6 {
+    type Input = Instance;
+    type Output = usize;
+
+    fn parse(s: &str) -> Result<Instance> {
+        return Instance::parse(s);
+    }
+
+    fn part1(instance: &Instance) -> Result<usize> {
+        return Ok(part1(instance));
+    }
+
+    fn part2(instance: &Instance) -> Option<Result<usize>> {
+        return Some(Ok(part2(instance)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::fs;
+
+    fn read_small_example() -> Instance {
+        let s = fs::read_to_string("inputs/day6-small.txt").unwrap();
+        return Instance::parse(&s).unwrap();
+    }
+
+    /// Walks step by step from the start, remembering every state.
+    fn loops_naively(instance: &Instance, obstacle: &Point) -> bool {
+        let board = instance.board.with_obstacle(obstacle);
+        let mut history = HashSet::new();
+        return board
+            .walk(Some(instance.guards[0]))
+            .any(|guard| !history.insert(guard));
+    }
+
+    #[test]
+    fn jumps_stop_in_front_of_obstacles() {
+        let instance = read_small_example();
+        let board = &instance.board;
+        let jumps = Jumps::new(board);
+        let guard = instance.guards[0];
+        assert_eq!(guard.pos, Point(6, 4));
+        let nowhere = Point(0, 0);
+        assert_eq!(jumps.stop(board, &guard, &nowhere), Some(Point(1, 4)));
+        let right = Guard {
+            pos: Point(1, 4),
+            dir: Right,
+        };
+        assert_eq!(jumps.stop(board, &right, &nowhere), Some(Point(1, 8)));
+        assert_eq!(jumps.stop(board, &right, &Point(1, 6)), Some(Point(1, 5)));
+        let left = Guard {
+            pos: Point(9, 5),
+            dir: Left,
+        };
+        assert_eq!(jumps.stop(board, &left, &nowhere), None);
+    }
+
+    #[test]
+    fn fast_loop_detection_agrees_with_walking() {
+        let mut instance = read_small_example();
+        let start = instance.guards[0];
+        let rules = [Turn::Right, Turn::Left, Turn::Around]
+            .into_iter()
+            .flat_map(|turn| [Movement::Orthogonal, Movement::Diagonal].map(|m| (turn, m)))
+            .flat_map(|(turn, movement)| {
+                [Collision::TurnInPlace, Collision::TurnAndStep].map(|collision| Rules {
+                    turn,
+                    movement,
+                    collision,
+                })
+            });
+        for rules in rules {
+            for topology in [Topology::Exit, Topology::Walls] {
+                instance.board.topology = topology;
+                instance.board.rules = rules;
+                let board = &instance.board;
+                let jumps = Jumps::new(board);
+                for (obstacle, &taken) in board.obstacles.iter() {
+                    if taken || obstacle == start.pos {
+                        continue;
+                    }
+                    assert_eq!(
+                        jumps.loops(board, &obstacle, start),
+                        loops_naively(&instance, &obstacle),
+                        "{:?} {:?} {:?}",
+                        board.rules,
+                        topology,
+                        obstacle
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rules_change_the_walk() {
+        let mut instance = read_small_example();
+        let walk = |instance: &Instance| -> Vec<Guard> {
+            return instance
+                .board
+                .walk(Some(instance.guards[0]))
+                .skip(5)
+                .take(2)
+                .collect();
+        };
+        let guard = |row, column, dir| Guard {
+            pos: Point(row, column),
+            dir,
+        };
+        assert_eq!(walk(&instance), vec![guard(1, 4, Up), guard(1, 5, Right)]);
+        instance.board.rules.collision = Collision::TurnInPlace;
+        assert_eq!(walk(&instance), vec![guard(1, 4, Up), guard(1, 4, Right)]);
+        instance.board.rules.collision = Collision::TurnAndStep;
+        instance.board.rules.turn = Turn::Left;
+        assert_eq!(walk(&instance), vec![guard(1, 4, Up), guard(1, 3, Left)]);
+        instance.board.rules = Rules {
+            movement: Movement::Diagonal,
+            ..Rules::PUZZLE
+        };
+        let diagonal: Vec<Guard> = instance
+            .board
+            .walk(Some(instance.guards[0]))
+            .take(2)
+            .collect();
+        assert_eq!(diagonal, vec![guard(6, 4, Up), guard(5, 5, Up)]);
+    }
+
+    #[test]
+    fn render_loop_with_obstruction() {
+        let instance = read_small_example();
+        let obstruction = Point(6, 3);
+        let board = instance.board.with_obstacle(&obstruction);
+        let (trail, looped) = patrol(&board, &instance.guards, |_, _| {});
+        assert!(looped);
+        assert_eq!(
+            render(&board, &trail, &[], Some(&obstruction)),
+            "....#.....\n\
+             ....+---+#\n\
+             ....|...|.\n\
+             ..#.|...|.\n\
+             ....|..#|.\n\
+             ....|...|.\n\
+             .#.O+---+.\n\
+             ........#.\n\
+             #.........\n\
+             ......#...\n"
+        );
+    }
+
+    #[test]
+    fn loops_of_small_example() {
+        let loops = loops(&read_small_example());
+        let obstructions: Vec<Point> = loops.iter().map(|l| l.obstruction).collect();
+        assert_eq!(
+            obstructions,
+            vec![
+                Point(6, 3),
+                Point(7, 6),
+                Point(7, 7),
+                Point(8, 1),
+                Point(8, 3),
+                Point(9, 7)
+            ]
+        );
+        assert_eq!(
+            loops[0],
+            Loop {
+                obstruction: Point(6, 3),
+                length: 18,
+                entry: vec![Guard {
+                    pos: Point(5, 4),
+                    dir: Up
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn guards_collide() {
+        let mut instance = Instance::parse(".....\n>...<\n.....\n").unwrap();
+        // Turning in place shows where the guards stop for each other.
+        instance.board.rules.collision = Collision::TurnInPlace;
+        let guard = |row, column, dir| {
+            Some(Guard {
+                pos: Point(row, column),
+                dir,
+            })
+        };
+        let walk: Vec<Vec<Option<Guard>>> = instance.board.walk_all(&instance.guards).collect();
+        assert_eq!(
+            walk,
+            vec![
+                vec![guard(1, 0, Right), guard(1, 4, Left)],
+                vec![guard(1, 1, Right), guard(1, 3, Left)],
+                vec![guard(1, 1, Down), guard(1, 3, Up)],
+                vec![guard(2, 1, Down), guard(0, 3, Up)],
+            ]
+        );
+        assert_eq!(part1(&instance), 6);
+    }
+
+    #[test]
+    fn guards_block_each_other() {
+        let second = |s: &str, collision| -> Vec<Guard> {
+            let mut instance = Instance::parse(s).unwrap();
+            instance.board.rules.collision = collision;
+            let walk: Vec<Vec<Option<Guard>>> =
+                instance.board.walk_all(&instance.guards).take(2).collect();
+            return walk[1].iter().flatten().copied().collect();
+        };
+        let guard = |row, column, dir| Guard {
+            pos: Point(row, column),
+            dir,
+        };
+        // Guards in a line step together, each into the cell the one ahead
+        // leaves.
+        assert_eq!(
+            second(">>..\n", Collision::TurnAndStep),
+            vec![guard(0, 1, Right), guard(0, 2, Right)]
+        );
+        // A guard that stays where it is blocks the one behind it.
+        assert_eq!(
+            second(">>#\n", Collision::TurnInPlace),
+            vec![guard(0, 0, Down), guard(0, 1, Down)]
+        );
+        // Guards cannot walk through each other.
+        assert_eq!(
+            second("..\n><\n..\n", Collision::TurnAndStep),
+            vec![guard(2, 0, Down), guard(0, 1, Up)]
+        );
+    }
+
+    #[test]
+    fn walking_finds_the_same_traps() {
+        let instance = read_small_example();
+        let slow = obstructions(&instance)
+            .into_iter()
+            .filter(|&o| trace(&instance, o).is_some())
+            .count();
+        assert_eq!(slow, part2(&instance));
+    }
+
+    #[test]
+    fn parse_accepts_every_glyph() {
+        let instance = Instance::parse("^>\nv<\n").unwrap();
+        let dirs: Vec<Dir> = instance.guards.iter().map(|g| g.dir).collect();
+        assert_eq!(dirs, vec![Up, Right, Down, Left]);
+        assert!(Instance::parse("V.\n").is_err());
+        let instance = Instance::parse_with("V.\n", &[('V', Down)]).unwrap();
+        assert_eq!(instance.guards[0].dir, Down);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = Instance::parse("..#\n.x^\n").err().unwrap();
+        assert_eq!(err, InputError::new(2, 2, "x", "unknown character"));
+        let err = Instance::parse("...\n..\n").err().unwrap();
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (2, "expected a row of 3 cells, found 2")
+        );
+        let err = Instance::parse("..#\n...\n").err().unwrap();
+        assert_eq!(err.message, "expected at least one guard");
+    }
+
+    #[test]
+    fn topologies() {
+        let mut instance = Instance::parse("...\n.^.\n...\n").unwrap();
+        assert_eq!(part1(&instance), 2);
+        instance.board.topology = Topology::Torus;
+        assert_eq!(part1(&instance), 3);
+        assert_eq!(part2(&instance), 2);
+        let guard = |row, column| Guard {
+            pos: Point(row, column),
+            dir: Right,
+        };
+        assert_eq!(
+            loops(&instance),
+            vec![
+                Loop {
+                    obstruction: Point(0, 1),
+                    length: 3,
+                    entry: vec![guard(1, 2)],
+                },
+                Loop {
+                    obstruction: Point(2, 1),
+                    length: 3,
+                    entry: vec![guard(0, 2)],
+                },
+            ]
+        );
+        instance.board.topology = Topology::Walls;
+        assert_eq!(part1(&instance), 9);
+        assert_eq!(part2(&instance), 8);
+        assert_eq!(loops(&instance).len(), 8);
+        let walk: Vec<Guard> = instance
+            .board
+            .walk(Some(instance.guards[0]))
+            .take(4)
+            .collect();
+        let pos: Vec<Point> = walk.iter().map(|g| g.pos).collect();
+        assert_eq!(
+            pos,
+            vec![Point(1, 1), Point(0, 1), Point(0, 2), Point(1, 2)]
+        );
+        assert_eq!(walk[2].dir, Right);
+    }
+
+    #[test]
+    fn part1_on_small_example() {
+        assert_eq!(part1(&read_small_example()), 41);
+    }
+
+    #[test]
+    fn part2_on_small_example() {
+        assert_eq!(part2(&read_small_example()), 6);
+    }
 }
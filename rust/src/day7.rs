@@ -1,69 +1,603 @@
-use std::env::args;
-use std::fs::File;
-use std::io;
-use std::io::BufRead;
-use std::process::exit;
+use crate::parse::{Line, lines};
+use crate::registry::{ToolArgs, ToolResult};
+use crate::{InputError, Result, Solution};
+use std::fmt;
+use std::ops::ControlFlow;
+use std::str::FromStr;
+
+/// Unsigned integers the solver can work with. All arithmetic is checked, so
+/// that results which do not fit are rejected instead of wrapping around.
+pub trait Number: Copy + Ord + fmt::Display + fmt::Debug + FromStr + Into<u128> {
+    const ZERO: Self;
+    const ONE: Self;
+    const TWO: Self;
+    const TEN: Self;
+
+    fn checked_add(self, y: Self) -> Option<Self>;
+    fn checked_sub(self, y: Self) -> Option<Self>;
+    fn checked_mul(self, y: Self) -> Option<Self>;
+    fn checked_div(self, y: Self) -> Option<Self>;
+    fn checked_rem(self, y: Self) -> Option<Self>;
+    fn checked_pow(self, exp: u32) -> Option<Self>;
+    fn checked_ilog10(self) -> Option<u32>;
+    fn xor(self, y: Self) -> Self;
+    fn to_u32(self) -> Option<u32>;
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {$(
+        impl Number for $t {
+            const ZERO: $t = 0;
+            const ONE: $t = 1;
+            const TWO: $t = 2;
+            const TEN: $t = 10;
+
+            fn checked_add(self, y: $t) -> Option<$t> {
+                return <$t>::checked_add(self, y);
+            }
+
+            fn checked_sub(self, y: $t) -> Option<$t> {
+                return <$t>::checked_sub(self, y);
+            }
+
+            fn checked_mul(self, y: $t) -> Option<$t> {
+                return <$t>::checked_mul(self, y);
+            }
+
+            fn checked_div(self, y: $t) -> Option<$t> {
+                return <$t>::checked_div(self, y);
+            }
+
+            fn checked_rem(self, y: $t) -> Option<$t> {
+                return <$t>::checked_rem(self, y);
+            }
+
+            fn checked_pow(self, exp: u32) -> Option<$t> {
+                return <$t>::checked_pow(self, exp);
+            }
+
+            fn checked_ilog10(self) -> Option<u32> {
+                return <$t>::checked_ilog10(self);
+            }
+
+            fn xor(self, y: $t) -> $t {
+                return self ^ y;
+            }
+
+            fn to_u32(self) -> Option<u32> {
+                return u32::try_from(self).ok();
+            }
+        }
+    )*};
+}
+
+impl_number!(u64, u128);
+
+/// The values of `x` for which `x op y == z`, for given `y` and `z`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Inverse<N> {
+    None,
+    One(N),
+    /// Every `x` works, e.g. when multiplying by zero.
+    Any,
+}
+
+/// A binary operator on the numbers of an equation.
+///
+/// Besides evaluating, an operator must be able to undo itself: given the
+/// result and the right operand, recover the left operand. This is what lets
+/// the solver search backwards from the target.
+pub trait Operator: Copy + fmt::Display {
+    /// Evaluates `x op y`, or `None` if the result does not fit in `N`.
+    fn apply<N: Number>(&self, x: N, y: N) -> Option<N>;
+
+    fn unapply<N: Number>(&self, z: N, y: N) -> Inverse<N>;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Mul,
+    Concat,
+    Sub,
+    Xor,
+    Pow,
+}
+
+use Op::*;
+
+impl Op {
+    pub const PART1: [Op; 2] = [Add, Mul];
+    pub const PART2: [Op; 3] = [Add, Mul, Concat];
+    const VALUES: [Op; 6] = [Add, Mul, Concat, Sub, Xor, Pow];
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Add => "+",
+            Mul => "*",
+            Concat => "||",
+            Sub => "-",
+            Xor => "^",
+            Pow => "**",
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.symbol());
+    }
+}
+
+impl FromStr for Op {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Op, String> {
+        return Op::VALUES
+            .into_iter()
+            .find(|op| op.symbol() == s)
+            .ok_or_else(|| format!("unknown operator `{}`", s));
+    }
+}
+
+impl Operator for Op {
+    fn apply<N: Number>(&self, x: N, y: N) -> Option<N> {
+        match self {
+            Add => x.checked_add(y),
+            Mul => x.checked_mul(y),
+            Concat => x.checked_mul(shift(y)?)?.checked_add(y),
+            Sub => x.checked_sub(y),
+            Xor => Some(x.xor(y)),
+            Pow => match y.to_u32() {
+                Some(y) => x.checked_pow(y),
+                None if x <= N::ONE => Some(x),
+                None => None,
+            },
+        }
+    }
+
+    fn unapply<N: Number>(&self, z: N, y: N) -> Inverse<N> {
+        let inverse = match self {
+            Add => z.checked_sub(y),
+            Mul if y == N::ZERO && z == N::ZERO => return Inverse::Any,
+            Mul => z
+                .checked_rem(y)
+                .filter(|&r| r == N::ZERO)
+                .and_then(|_| z.checked_div(y)),
+            Concat => strip_suffix(z, y),
+            Sub => z.checked_add(y),
+            Xor => Some(z.xor(y)),
+            Pow if y == N::ZERO && z == N::ONE => return Inverse::Any,
+            Pow => root(z, y),
+        };
+        return inverse.map_or(Inverse::None, Inverse::One);
+    }
+}
+
+/// The power of ten by which `x` is shifted when `y` is concatenated to it,
+/// or `None` if that does not fit in `N`.
+fn shift<N: Number>(y: N) -> Option<N> {
+    return N::TEN.checked_pow(y.checked_ilog10().unwrap_or(0) + 1);
+}
+
+/// Returns `x` such that concatenating the digits of `x` and `y` gives `z`.
+fn strip_suffix<N: Number>(z: N, y: N) -> Option<N> {
+    // A shift that overflows is larger than `z`, so `z` cannot end in `y`.
+    // If `z` is `y`, then `x` is 0, as in `0 || y`.
+    let shift = shift(y)?;
+    if z.checked_rem(shift)? == y {
+        return z.checked_div(shift);
+    }
+    return None;
+}
+
+/// Returns `x` such that `x` to the power `y` is `z`.
+fn root<N: Number>(z: N, y: N) -> Option<N> {
+    if y == N::ZERO {
+        return None;
+    }
+    if z <= N::ONE {
+        return Some(z);
+    }
+    // Only 0 and 1 have powers beyond `u32::MAX` that fit.
+    let y = y.to_u32()?;
+    // Binary search for the largest `x` whose power is at most `z`. Powers
+    // that overflow are certainly larger.
+    let (mut lo, mut hi) = (N::ONE, z);
+    while lo < hi {
+        let mid = hi.checked_sub(hi.checked_sub(lo)?.checked_div(N::TWO)?)?;
+        if mid.checked_pow(y).is_some_and(|p| p <= z) {
+            lo = mid;
+        } else {
+            hi = mid.checked_sub(N::ONE)?;
+        }
+    }
+    return Some(lo).filter(|x| x.checked_pow(y) == Some(z));
+}
 
 #[derive(Debug)]
-struct Equation {
-    lhs: u64,
-    rhs: Vec<u64>,
-}
-
-impl Equation {
-    fn parse(s: &str) -> Equation {
-        let mut split = s.split(':');
-        let lhs = str::parse(split.next().unwrap()).unwrap();
-        let rhs = split
-            .next()
-            .unwrap()
+pub struct Equation<N: Number = u64> {
+    lhs: N,
+    rhs: Vec<N>,
+    /// The line of the input the equation is on.
+    line: usize,
+}
+
+impl<N: Number> Equation<N> {
+    fn parse(line: Line) -> Result<Equation<N>> {
+        let (lhs, rhs) = line.split_once(':')?;
+        let lhs = lhs.parse()?;
+        let rhs: Vec<N> = rhs
             .split(' ')
-            .filter(|s| !s.trim().is_empty())
-            .map(|s| str::parse(s).unwrap())
+            .map(|s| s.parse())
             .rev()
-            .collect();
-        return Equation { lhs: lhs, rhs: rhs };
+            .collect::<Result<_>>()?;
+        if rhs.is_empty() {
+            return Err(line.error("expected at least one number"));
+        }
+        return Ok(Equation {
+            lhs,
+            rhs,
+            line: line.number,
+        });
     }
 
-    fn read<S: io::Read>(stream: &mut S) -> impl Iterator<Item = Equation> {
-        let reader = io::BufReader::new(stream);
-        return reader.lines().map(|res| Equation::parse(&res.unwrap()));
+    fn read(s: &str) -> impl Iterator<Item = Result<Equation<N>>> {
+        return lines(s).map(Equation::parse);
     }
 
-    fn possible(&self) -> bool {
-        fn pred(lhs: u64, rhs: &[u64]) -> bool {
+    /// Calls `visit` with every choice from `ops`, in left to right order,
+    /// that makes the equation true, until it breaks. Since the operators are
+    /// evaluated left to right, the search works backwards from `lhs`, undoing
+    /// the last operator at every step.
+    fn search<O: Operator>(
+        &self,
+        ops: &[O],
+        visit: &mut dyn FnMut(&[O]) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        fn emit<O: Operator>(
+            prefix: &[O],
+            suffix: &[O],
+            visit: &mut dyn FnMut(&[O]) -> ControlFlow<()>,
+        ) -> ControlFlow<()> {
+            let witness: Vec<O> = prefix.iter().chain(suffix.iter().rev()).copied().collect();
+            return visit(&witness);
+        }
+
+        // Once an operator accepts any left operand, every evaluation of the
+        // remaining numbers completes a solution, so these are enumerated
+        // forwards.
+        fn forward<N: Number, O: Operator>(
+            acc: N,
+            operands: &[N],
+            ops: &[O],
+            prefix: &mut Vec<O>,
+            suffix: &[O],
+            visit: &mut dyn FnMut(&[O]) -> ControlFlow<()>,
+        ) -> ControlFlow<()> {
+            if operands.is_empty() {
+                return emit(prefix, suffix, visit);
+            }
+            for &op in ops {
+                if let Some(next) = op.apply(acc, operands[0]) {
+                    prefix.push(op);
+                    let flow = forward(next, &operands[1..], ops, prefix, suffix, visit);
+                    prefix.pop();
+                    flow?;
+                }
+            }
+            return ControlFlow::Continue(());
+        }
+
+        fn backward<N: Number, O: Operator>(
+            lhs: N,
+            rhs: &[N],
+            ops: &[O],
+            suffix: &mut Vec<O>,
+            visit: &mut dyn FnMut(&[O]) -> ControlFlow<()>,
+        ) -> ControlFlow<()> {
             if rhs.len() == 1 {
-                return lhs == rhs[0];
+                if lhs == rhs[0] {
+                    return emit(&[], suffix, visit);
+                }
+                return ControlFlow::Continue(());
             };
-            if lhs > rhs[0] && pred(lhs - rhs[0], &rhs[1..]) {
-                return true;
-            }
-            if lhs % rhs[0] == 0 && pred(lhs / rhs[0], &rhs[1..]) {
-                return true;
+            for &op in ops {
+                suffix.push(op);
+                let flow = match op.unapply(lhs, rhs[0]) {
+                    Inverse::None => ControlFlow::Continue(()),
+                    Inverse::One(prev) => backward(prev, &rhs[1..], ops, suffix, visit),
+                    Inverse::Any => {
+                        let operands: Vec<N> = rhs[1..].iter().rev().copied().collect();
+                        let mut prefix = Vec::new();
+                        forward(operands[0], &operands[1..], ops, &mut prefix, suffix, visit)
+                    }
+                };
+                suffix.pop();
+                flow?;
             }
-            return false;
+            return ControlFlow::Continue(());
         }
 
-        return pred(self.lhs, &self.rhs);
+        return backward(self.lhs, &self.rhs, ops, &mut Vec::new(), visit);
+    }
+
+    /// Whether some choice of operators makes the equation true.
+    fn possible<O: Operator>(&self, ops: &[O]) -> bool {
+        return self.witness(ops).is_some();
+    }
+
+    /// The first choice of operators that makes the equation true.
+    pub fn witness<O: Operator>(&self, ops: &[O]) -> Option<Vec<O>> {
+        let mut witness = None;
+        let _ = self.search(ops, &mut |found| {
+            witness = Some(found.to_vec());
+            return ControlFlow::Break(());
+        });
+        return witness;
+    }
+
+    /// Every choice of operators that makes the equation true.
+    pub fn witnesses<O: Operator>(&self, ops: &[O]) -> Vec<Vec<O>> {
+        let mut witnesses = Vec::new();
+        let _ = self.search(ops, &mut |found| {
+            witnesses.push(found.to_vec());
+            return ControlFlow::Continue(());
+        });
+        return witnesses;
+    }
+
+    pub fn count_solutions<O: Operator>(&self, ops: &[O]) -> usize {
+        let mut count = 0;
+        let _ = self.search(ops, &mut |_| {
+            count += 1;
+            return ControlFlow::Continue(());
+        });
+        return count;
+    }
+
+    /// Writes out the right hand side with the given operators, e.g.
+    /// `81 + 40 * 27`.
+    pub fn format<O: Operator>(&self, ops: &[O]) -> String {
+        let mut operands = self.rhs.iter().rev();
+        let mut s = operands.next().map(|x| x.to_string()).unwrap_or_default();
+        for (op, x) in ops.iter().zip(operands) {
+            s += &format!(" {} {}", op, x);
+        }
+        return s;
     }
 }
 
-fn part1(path: &str) -> u64 {
-    let mut file = File::open(&path).unwrap();
-    return Equation::read(&mut file)
-        .filter(|eqn| eqn.possible())
-        .map(|eqn| eqn.lhs)
-        .sum();
+/// The equations of an input, in the narrowest type that fits all numbers.
+pub enum Equations {
+    U64(Vec<Equation<u64>>),
+    U128(Vec<Equation<u128>>),
 }
 
-fn main() {
-    match args().nth(1) {
-        None => {
-            println!("Specify an input file");
-            exit(1);
+impl Equations {
+    fn read(s: &str) -> Result<Equations> {
+        if let Ok(equations) = Equation::read(s).collect() {
+            return Ok(Equations::U64(equations));
         }
-        Some(path) => {
-            println!("Part 1: {}", part1(&path));
+        return Ok(Equations::U128(Equation::read(s).collect::<Result<_>>()?));
+    }
+}
+
+/// The sum of the targets of the solvable equations. The sum is taken in
+/// `u128`, so that it cannot overflow for inputs that fit in `u64`. Beyond
+/// that it fails at the equation that makes it overflow.
+fn calibration<N: Number, O: Operator>(equations: &[Equation<N>], ops: &[O]) -> Result<u128> {
+    let mut total: u128 = 0;
+    for eqn in equations.iter().filter(|eqn| eqn.possible(ops)) {
+        total = total.checked_add(eqn.lhs.into()).ok_or_else(|| {
+            let lhs = eqn.lhs.to_string();
+            InputError::new(eqn.line, 1, &lhs, "the calibration result overflows u128")
+        })?;
+    }
+    return Ok(total);
+}
+
+fn solve<O: Operator>(equations: &Equations, ops: &[O]) -> Result<u128> {
+    match equations {
+        Equations::U64(equations) => calibration(equations, ops),
+        Equations::U128(equations) => calibration(equations, ops),
+    }
+}
+
+fn part1(equations: &Equations) -> Result<u128> {
+    return solve(equations, &Op::PART1);
+}
+
+fn part2(equations: &Equations) -> Result<u128> {
+    return solve(equations, &Op::PART2);
+}
+
+fn print_explanations<N: Number>(equations: &[Equation<N>], ops: &[Op]) {
+    for eqn in equations {
+        match eqn.witness(ops) {
+            None => println!("{}: no solution", eqn.lhs),
+            Some(witness) => match eqn.count_solutions(ops) {
+                1 => println!("{} = {}", eqn.lhs, eqn.format(&witness)),
+                n => println!("{} = {} ({} solutions)", eqn.lhs, eqn.format(&witness), n),
+            },
         }
     }
 }
+
+/// Prints, for every equation, an operator choice that makes it true and the
+/// number of such choices. Part 2 also allows concatenation; `--ops=+,*,-`
+/// replaces the operators of the puzzle by any other set.
+pub fn explain(s: &str, args: &ToolArgs) -> ToolResult {
+    let equations = Equations::read(s)?;
+    let custom: Option<Vec<Op>> = match args.value::<String>("ops") {
+        None => None,
+        Some(list) => Some(
+            list.split(',')
+                .map(str::parse)
+                .collect::<std::result::Result<_, _>>()?,
+        ),
+    };
+    for &part in &args.parts {
+        let ops: &[Op] = match (&custom, part) {
+            (Some(ops), _) => ops,
+            (None, 1) => &Op::PART1,
+            (None, _) => &Op::PART2,
+        };
+        println!("Part {}:", part);
+        match &equations {
+            Equations::U64(equations) => print_explanations(equations, ops),
+            Equations::U128(equations) => print_explanations(equations, ops),
+        }
+    }
+    return Ok(());
+}
+
+pub struct Day7;
+
+impl Solution for Day7 {
+    type Input = Equations;
+    type Output = u128;
+
+    fn parse(s: &str) -> Result<Equations> {
+        return Equations::read(s);
+    }
+
+    fn part1(equations: &Equations) -> Result<u128> {
+        return part1(equations);
+    }
+
+    fn part2(equations: &Equations) -> Option<Result<u128>> {
+        return Some(part2(equations));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn read_small_example() -> Vec<Equation> {
+        let s = fs::read_to_string("inputs/day7-small.txt").unwrap();
+        return Equation::read(&s).collect::<Result<_>>().unwrap();
+    }
+
+    fn read_small_input() -> Equations {
+        let s = fs::read_to_string("inputs/day7-small.txt").unwrap();
+        return Equations::read(&s).unwrap();
+    }
+
+    fn equation(s: &str) -> Equation {
+        return Equation::read(s).next().unwrap().unwrap();
+    }
+
+    #[test]
+    fn strip_suffix_of_concatenation() {
+        assert_eq!(strip_suffix(156u64, 6), Some(15));
+        assert_eq!(strip_suffix(1510u64, 10), Some(15));
+        assert_eq!(strip_suffix(156u64, 56), Some(1));
+        assert_eq!(strip_suffix(156u64, 156), Some(0));
+        assert_eq!(strip_suffix(56u64, 156), None);
+        assert_eq!(strip_suffix(156u64, 7), None);
+        assert_eq!(strip_suffix(u64::MAX, 6), None);
+        assert_eq!(strip_suffix(u64::MAX, 615), Some(18446744073709551));
+    }
+
+    #[test]
+    fn zero_is_a_number() {
+        assert_eq!(
+            equation("5: 0 5").witnesses(&[Add, Concat]),
+            vec![vec![Add], vec![Concat]]
+        );
+        assert_eq!(
+            equation("156: 0 156").witnesses(&[Concat, Mul]),
+            vec![vec![Concat]]
+        );
+        assert_eq!(
+            equation("0: 0 156 0").witnesses(&[Concat, Mul]),
+            vec![vec![Mul, Concat], vec![Concat, Mul], vec![Mul, Mul]]
+        );
+    }
+
+    #[test]
+    fn overflow_is_rejected() {
+        assert_eq!(Mul.apply(u64::MAX, 2), None);
+        assert_eq!(Concat.apply(u64::MAX / 10, 99), None);
+        assert_eq!(Pow.apply(2u64, 64), None);
+        assert_eq!(Pow.apply(2u128, 64), Some(1 << 64));
+        assert_eq!(Pow.unapply(u128::MAX, 2), Inverse::None);
+        assert_eq!(Pow.unapply(1u128 << 126, 2), Inverse::One(1 << 63));
+        // Both operators overflow, so wrapping arithmetic would find a solution.
+        let eqn = equation("0: 18446744073709551615 2 0");
+        assert_eq!(eqn.count_solutions(&[Add, Mul]), 0);
+    }
+
+    #[test]
+    fn targets_beyond_u64() {
+        let s = "36893488147419103232: 18446744073709551616 2\n190: 10 19\n";
+        let equations = Day7::parse(s).unwrap();
+        assert!(matches!(equations, Equations::U128(_)));
+        assert_eq!(part1(&equations), Ok(36893488147419103232 + 190));
+        let err = Day7::parse("1: 340282366920938463463374607431768211456 1").err();
+        assert_eq!(err.map(|e| e.column), Some(4));
+    }
+
+    #[test]
+    fn calibration_overflow_is_an_error() {
+        let max = u128::MAX;
+        let s = format!("190: 10 19\n{max}: {max}\n{max}: {max}\n");
+        let equations = Day7::parse(&s).unwrap();
+        let err = part1(&equations).err();
+        assert_eq!(err.map(|e| (e.line, e.column)), Some((2, 1)));
+        assert!(crate::Puzzle::solve(&Day7, &s, &[2]).is_err());
+    }
+
+    #[test]
+    fn unapply_inverts_apply() {
+        for op in Op::VALUES {
+            for (x, y) in [(0u64, 0u64), (1, 5), (12, 3), (7, 1), (3, 4), (1000, 25)] {
+                if let Some(z) = op.apply(x, y) {
+                    let inverse = op.unapply(z, y);
+                    assert!(
+                        inverse == Inverse::One(x) || inverse == Inverse::Any,
+                        "{} {} {}",
+                        x,
+                        op,
+                        y
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn witnesses_of_small_example() {
+        let equations = read_small_example();
+        let eqn = &equations[1];
+        assert_eq!(
+            eqn.witnesses(&Op::PART1),
+            vec![vec![Mul, Add], vec![Add, Mul]]
+        );
+        assert_eq!(eqn.format(&[Add, Mul]), "81 + 40 * 27");
+        assert_eq!(equations[2].witness(&Op::PART2), None);
+        assert_eq!(equations[3].witness(&Op::PART2), Some(vec![Concat]));
+        assert_eq!(equations[4].count_solutions(&Op::PART2), 1);
+    }
+
+    #[test]
+    fn other_operator_sets() {
+        assert_eq!(equation("7: 10 3").witness(&[Add, Sub]), Some(vec![Sub]));
+        assert_eq!(equation("9: 10 3").witness(&[Xor]), Some(vec![Xor]));
+        assert_eq!(equation("81: 3 2 2").witness(&[Pow]), Some(vec![Pow, Pow]));
+        assert_eq!(equation("0: 5 2 0").count_solutions(&[Add, Mul]), 2);
+    }
+
+    #[test]
+    fn part1_on_small_example() {
+        assert_eq!(part1(&read_small_input()), Ok(3749));
+    }
+
+    #[test]
+    fn part2_on_small_example() {
+        assert_eq!(part2(&read_small_input()), Ok(11387));
+    }
+}
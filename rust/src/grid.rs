@@ -0,0 +1,339 @@
+use crate::Result;
+use crate::parse::{InputError, lines};
+use std::fmt;
+use std::ops::{Add, Index, IndexMut, Mul, Sub};
+use std::str::FromStr;
+
+/// A position on a grid as `(row, column)`, or the offset between two
+/// positions. Coordinates are signed, so that stepping off the top or left
+/// edge gives a point that is simply not on the grid.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
+pub struct Point(pub i32, pub i32);
+
+pub const UP: Point = Point(-1, 0);
+pub const RIGHT: Point = Point(0, 1);
+pub const DOWN: Point = Point(1, 0);
+pub const LEFT: Point = Point(0, -1);
+
+/// The four directions along rows and columns, clockwise from up.
+pub const ORTHOGONAL: [Point; 4] = [UP, RIGHT, DOWN, LEFT];
+
+/// All eight directions, clockwise from up.
+pub const DIRECTIONS: [Point; 8] = [
+    UP,
+    Point(-1, 1),
+    RIGHT,
+    Point(1, 1),
+    DOWN,
+    Point(1, -1),
+    LEFT,
+    Point(-1, -1),
+];
+
+impl Point {
+    /// The four points next to this one along rows and columns.
+    pub fn neighbours(self) -> impl Iterator<Item = Point> {
+        return ORTHOGONAL.into_iter().map(move |dir| self + dir);
+    }
+
+    /// The eight points around this one, diagonals included.
+    pub fn neighbours8(self) -> impl Iterator<Item = Point> {
+        return DIRECTIONS.into_iter().map(move |dir| self + dir);
+    }
+
+    /// The number of steps between two points when diagonal steps are
+    /// allowed.
+    pub fn chebyshev(self, other: Point) -> u32 {
+        return self.0.abs_diff(other.0).max(self.1.abs_diff(other.1));
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        return Point(self.0 + other.0, self.1 + other.1);
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        return Point(self.0 - other.0, self.1 - other.1);
+    }
+}
+
+impl Mul<i32> for Point {
+    type Output = Point;
+
+    fn mul(self, k: i32) -> Point {
+        return Point(self.0 * k, self.1 * k);
+    }
+}
+
+impl FromStr for Point {
+    type Err = String;
+
+    /// Parses `row,column`, counted from 0.
+    fn from_str(s: &str) -> std::result::Result<Point, String> {
+        let error = || format!("expected `row,column`, got `{}`", s);
+        let (row, column) = s.split_once(',').ok_or_else(error)?;
+        let row = row.trim().parse().map_err(|_| error())?;
+        let column = column.trim().parse().map_err(|_| error())?;
+        return Ok(Point(row, column));
+    }
+}
+
+/// A rectangular grid, stored row by row in a single vector.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Grid<T> {
+    height: usize,
+    width: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(height: usize, width: usize, value: T) -> Grid<T>
+    where
+        T: Clone,
+    {
+        return Grid {
+            height,
+            width,
+            cells: vec![value; height * width],
+        };
+    }
+
+    /// Builds a grid from its rows, or `None` if they differ in width.
+    pub fn from_rows(rows: impl IntoIterator<Item = Vec<T>>) -> Option<Grid<T>> {
+        let mut grid = Grid {
+            height: 0,
+            width: 0,
+            cells: Vec::new(),
+        };
+        for row in rows {
+            if grid.height == 0 {
+                grid.width = row.len();
+            } else if row.len() != grid.width {
+                return None;
+            }
+            grid.cells.extend(row);
+            grid.height += 1;
+        }
+        return Some(grid);
+    }
+
+    /// Parses one cell per character, one row per line. `cell` is given the
+    /// position and character of every cell, and its errors are reported at
+    /// that position. All rows must have the same width.
+    pub fn parse(
+        s: &str,
+        mut cell: impl FnMut(Point, char) -> std::result::Result<T, String>,
+    ) -> Result<Grid<T>> {
+        let mut cells = Vec::new();
+        let mut height = 0;
+        let mut width = None;
+        for line in lines(s) {
+            let row = line.number as i32 - 1;
+            let mut count = 0;
+            for (column, c) in line.text.chars().enumerate() {
+                let value = cell(Point(row, column as i32), c).map_err(|message| {
+                    InputError::new(line.number, column + 1, &c.to_string(), message)
+                })?;
+                cells.push(value);
+                count += 1;
+            }
+            match width {
+                None => width = Some(count),
+                Some(width) if width != count => {
+                    let message = format!("expected a row of {} cells, found {}", width, count);
+                    return Err(line.token().error(&message));
+                }
+                Some(_) => {}
+            }
+            height += 1;
+        }
+        return Ok(Grid {
+            height,
+            width: width.unwrap_or(0),
+            cells,
+        });
+    }
+
+    pub fn height(&self) -> usize {
+        return self.height;
+    }
+
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.cells.is_empty();
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        return 0 <= p.0 && (p.0 as usize) < self.height && 0 <= p.1 && (p.1 as usize) < self.width;
+    }
+
+    /// The position of `p` in row-major order.
+    pub fn index(&self, p: Point) -> Option<usize> {
+        if !self.contains(p) {
+            return None;
+        }
+        return Some(p.0 as usize * self.width + p.1 as usize);
+    }
+
+    /// The point at position `i` in row-major order.
+    pub fn point(&self, i: usize) -> Point {
+        return Point((i / self.width) as i32, (i % self.width) as i32);
+    }
+
+    pub fn get(&self, p: Point) -> Option<&T> {
+        return self.index(p).map(|i| &self.cells[i]);
+    }
+
+    pub fn get_mut(&mut self, p: Point) -> Option<&mut T> {
+        return self.index(p).map(|i| &mut self.cells[i]);
+    }
+
+    /// All cells in row-major order.
+    pub fn cells(&self) -> &[T] {
+        return &self.cells;
+    }
+
+    /// All points of the grid in row-major order.
+    pub fn points(&self) -> impl DoubleEndedIterator<Item = Point> + '_ {
+        return (0..self.cells.len()).map(|i| self.point(i));
+    }
+
+    /// All cells with their points, in row-major order.
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        return self.points().zip(self.cells.iter());
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        return self.cells.chunks(self.width.max(1));
+    }
+
+    /// The points from `start` on, `dir` apart, for as long as they are on
+    /// the grid.
+    pub fn ray(&self, start: Point, dir: Point) -> impl Iterator<Item = Point> + '_ {
+        return std::iter::successors(Some(start), move |&p| Some(p + dir))
+            .take_while(|&p| self.contains(p));
+    }
+
+    /// The orthogonal neighbours of `p` that are on the grid.
+    pub fn neighbours(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+        return p.neighbours().filter(|&q| self.contains(q));
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        return Grid {
+            height: self.height,
+            width: self.width,
+            cells: self.cells.iter().map(f).collect(),
+        };
+    }
+}
+
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, p: Point) -> &T {
+        match self.get(p) {
+            Some(cell) => cell,
+            None => panic!("{:?} is not on the grid", p),
+        }
+    }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+    fn index_mut(&mut self, p: Point) -> &mut T {
+        match self.get_mut(p) {
+            Some(cell) => cell,
+            None => panic!("{:?} is not on the grid", p),
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.rows() {
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Result<Grid<char>> {
+        return Grid::parse(s, |_, c| Ok(c));
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let grid = chars("ab\ncd\nef\n").unwrap();
+        assert_eq!((grid.height(), grid.width()), (3, 2));
+        assert_eq!(grid[Point(2, 0)], 'e');
+        assert_eq!(grid.get(Point(0, 2)), None);
+        assert_eq!(grid.get(Point(-1, 0)), None);
+        assert_eq!(grid.to_string(), "ab\ncd\nef\n");
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_cell() {
+        let err = chars("ab\nc\n").err().unwrap();
+        assert_eq!((err.line, err.column), (2, 1));
+        let err = Grid::parse("ab\ncd", |_, c| match c {
+            'd' => Err("no d".to_string()),
+            _ => Ok(c),
+        });
+        assert_eq!(err, Err(InputError::new(2, 2, "d", "no d")));
+    }
+
+    #[test]
+    fn empty_grid() {
+        let grid = chars("").unwrap();
+        assert!(grid.is_empty());
+        assert_eq!(grid.points().count(), 0);
+        assert!(!grid.contains(Point(0, 0)));
+        assert_eq!(grid.to_string(), "");
+    }
+
+    #[test]
+    fn points_are_row_major() {
+        let grid = Grid::new(2, 3, 0);
+        let points: Vec<Point> = grid.points().collect();
+        assert_eq!(
+            points[..4],
+            [Point(0, 0), Point(0, 1), Point(0, 2), Point(1, 0)]
+        );
+        assert_eq!(grid.index(Point(1, 2)), Some(5));
+        assert_eq!(grid.point(5), Point(1, 2));
+    }
+
+    #[test]
+    fn rays_stop_at_the_edge() {
+        let grid = Grid::new(3, 4, 0);
+        let ray: Vec<Point> = grid.ray(Point(2, 1), Point(-1, 1)).collect();
+        assert_eq!(ray, vec![Point(2, 1), Point(1, 2), Point(0, 3)]);
+        assert_eq!(grid.ray(Point(3, 0), UP).count(), 0);
+        assert_eq!(grid.neighbours(Point(0, 0)).count(), 2);
+        assert_eq!(Point(0, 0).neighbours8().count(), 8);
+    }
+
+    #[test]
+    fn from_rows_rejects_ragged_rows() {
+        assert!(Grid::from_rows([vec![1, 2], vec![3]]).is_none());
+        let grid = Grid::from_rows([vec![1, 2], vec![3, 4]]).unwrap();
+        assert_eq!(grid.map(|x| x * 10)[Point(1, 0)], 30);
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt::Display;
+
+pub mod answers;
+pub mod bench;
+pub mod day1;
+pub mod day2;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod day7;
+pub mod grid;
+pub mod parse;
+pub mod registry;
+
+use bench::Timings;
+pub use parse::InputError;
+
+pub type Result<T> = std::result::Result<T, InputError>;
+
+/// A puzzle of a single day: the input is parsed once and then shared by both
+/// parts.
+pub trait Solution {
+    type Input;
+    type Output: Display;
+
+    /// The parts that are solved. Days whose `part2` returns `None` leave out
+    /// part 2.
+    const PARTS: &'static [u32] = &[1, 2];
+
+    fn parse(s: &str) -> Result<Self::Input>;
+
+    /// Parts fail on input that parses but cannot be solved, reported at the
+    /// line that is to blame.
+    fn part1(input: &Self::Input) -> Result<Self::Output>;
+
+    /// Returns `None` for days whose second part has not been solved yet.
+    fn part2(input: &Self::Input) -> Option<Result<Self::Output>>;
+}
+
+/// Object safe view on a `Solution`, so that days with different input and
+/// output types can be stored side by side.
+pub trait Puzzle: Sync {
+    /// The parts that are solved, known without solving them.
+    fn parts(&self) -> &'static [u32];
+
+    fn solve(&self, s: &str, parts: &[u32]) -> Result<Vec<(u32, Option<String>)>>;
+
+    /// Times parsing and both parts separately, repeating each `runs` times.
+    fn time(&self, s: &str, runs: usize) -> Result<Timings>;
+}
+
+impl<S: Solution + Sync> Puzzle for S {
+    fn parts(&self) -> &'static [u32] {
+        return S::PARTS;
+    }
+
+    fn solve(&self, s: &str, parts: &[u32]) -> Result<Vec<(u32, Option<String>)>> {
+        let input = S::parse(s)?;
+        let mut answers = Vec::new();
+        for &part in parts {
+            let answer = match part {
+                1 => Some(S::part1(&input)?.to_string()),
+                2 => S::part2(&input).transpose()?.map(|x| x.to_string()),
+                _ => None,
+            };
+            answers.push((part, answer));
+        }
+        return Ok(answers);
+    }
+
+    fn time(&self, s: &str, runs: usize) -> Result<Timings> {
+        return bench::time::<S>(s, runs);
+    }
+}
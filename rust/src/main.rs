@@ -0,0 +1,227 @@
+use advent_of_code_2024::InputError;
+use advent_of_code_2024::answers::{self, Answers, Verdict};
+use advent_of_code_2024::bench::{self, Timings};
+use advent_of_code_2024::registry::{self, DAYS, Day, Tool, ToolArgs};
+use std::env::args;
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+const USAGE: &str = "Usage:
+    aoc list
+    aoc run <day|all> [--part <1|2>] [--check] [input]
+    aoc run <day> --<tool> [--<option>[=<value>]...] [--part <1|2>] [input]
+    aoc bench <day|all> [--runs <n>] [--output <file>]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    exit(1);
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    exit(1);
+}
+
+struct RunArgs {
+    days: Vec<&'static Day>,
+    parts: Vec<u32>,
+    input: Option<String>,
+    check: bool,
+    tool: Option<(&'static Tool, ToolArgs)>,
+}
+
+fn parse_days(arg: Option<&str>) -> Vec<&'static Day> {
+    match arg {
+        None => usage(),
+        Some("all") => DAYS.iter().collect(),
+        Some(s) => match s.parse().ok().and_then(registry::find) {
+            Some(day) => vec![day],
+            None => fail(&format!("Unknown day: {}", s)),
+        },
+    }
+}
+
+impl RunArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> RunArgs {
+        let days = parse_days(args.next().as_deref());
+        let mut parts = vec![1, 2];
+        let mut input = None;
+        let mut check = false;
+        let mut options = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--part" => match args.next().as_deref() {
+                    Some("1") => parts = vec![1],
+                    Some("2") => parts = vec![2],
+                    _ => usage(),
+                },
+                "--check" => check = true,
+                _ if input.is_none() && !arg.starts_with("--") => input = Some(arg),
+                _ if arg.starts_with("--") => match arg[2..].split_once('=') {
+                    Some((name, value)) => {
+                        options.push((name.to_string(), Some(value.to_string())))
+                    }
+                    None => options.push((arg[2..].to_string(), None)),
+                },
+                _ => usage(),
+            }
+        }
+        if input.is_some() && days.len() > 1 {
+            fail("An input file can only be given when running a single day");
+        }
+        let tool = match options.first() {
+            None => None,
+            Some((name, _)) if days.len() == 1 => match days[0].tool(name) {
+                Some(tool) => Some((
+                    tool,
+                    ToolArgs {
+                        parts: parts.clone(),
+                        options,
+                    },
+                )),
+                None => fail(&format!("Day {} has no tool --{}", days[0].day, name)),
+            },
+            Some(_) => fail("Tools can only be run for a single day"),
+        };
+        return RunArgs {
+            days,
+            parts,
+            input,
+            check,
+            tool,
+        };
+    }
+}
+
+struct BenchArgs {
+    days: Vec<&'static Day>,
+    runs: usize,
+    output: String,
+}
+
+impl BenchArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> BenchArgs {
+        let days = parse_days(args.next().as_deref());
+        let mut runs = 10;
+        let mut output = bench::DEFAULT_PATH.to_string();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--runs" => match args.next().and_then(|s| s.parse().ok()) {
+                    Some(n) if n > 0 => runs = n,
+                    _ => usage(),
+                },
+                "--output" => match args.next() {
+                    Some(path) => output = path,
+                    None => usage(),
+                },
+                _ => usage(),
+            }
+        }
+        return BenchArgs { days, runs, output };
+    }
+}
+
+fn read_input(path: &str) -> String {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) => fail(&format!("Cannot read {}: {}", path, err)),
+    }
+}
+
+fn read_answers() -> Answers {
+    let path = answers::DEFAULT_PATH;
+    let contents = read_input(path);
+    match Answers::parse(&contents) {
+        Ok(answers) => answers,
+        Err(err) => fail(&err.with_file(path).report(&contents)),
+    }
+}
+
+fn run(args: RunArgs) {
+    let answers = if args.check {
+        Some(read_answers())
+    } else {
+        None
+    };
+    let mut failed = false;
+    for day in args.days {
+        let path = args.input.clone().unwrap_or_else(|| day.default_input());
+        let contents = read_input(&path);
+        if let Some((tool, tool_args)) = &args.tool {
+            if let Err(err) = (tool.run)(&contents, tool_args) {
+                match err.downcast::<InputError>() {
+                    Ok(err) => fail(&err.with_file(&path).report(&contents)),
+                    Err(err) => fail(&err.to_string()),
+                }
+            }
+            continue;
+        }
+        let solved = match day.puzzle.solve(&contents, &args.parts) {
+            Ok(solved) => solved,
+            Err(err) => fail(&err.with_file(&path).report(&contents)),
+        };
+        for (part, answer) in solved {
+            let answer = answer.unwrap_or_else(|| "not solved".to_string());
+            match &answers {
+                None => println!("Day {} Part {}: {}", day.day, part, answer),
+                Some(answers) => {
+                    let verdict = answers.check(day.day, part, Path::new(&path), &answer);
+                    failed |= matches!(verdict, Verdict::Fail { .. });
+                    println!("Day {} Part {}: {} ({})", day.day, part, answer, verdict);
+                }
+            }
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+fn bench(args: BenchArgs) {
+    let mut results: Vec<(u32, Timings)> = Vec::new();
+    for day in args.days {
+        let path = day.default_input();
+        let contents = read_input(&path);
+        let timings = match day.puzzle.time(&contents, args.runs) {
+            Ok(timings) => timings,
+            Err(err) => fail(&err.with_file(&path).report(&contents)),
+        };
+        for (stage, sample) in timings.stages() {
+            println!(
+                "Day {} {}: {:?} (min {:?})",
+                day.day, stage, sample.median, sample.min
+            );
+        }
+        results.push((day.day, timings));
+    }
+    let tsv = bench::to_tsv(results.iter().map(|(day, timings)| (*day, timings)));
+    if let Err(err) = fs::write(&args.output, tsv) {
+        fail(&format!("Cannot write {}: {}", args.output, err));
+    }
+}
+
+fn list() {
+    for day in DAYS.iter() {
+        let parts: Vec<String> = day.puzzle.parts().iter().map(u32::to_string).collect();
+        println!(
+            "Day {}: parts {} ({})",
+            day.day,
+            parts.join(", "),
+            day.default_input()
+        );
+        for tool in day.tools {
+            println!("    --{}: {}", tool.name, tool.help);
+        }
+    }
+}
+
+fn main() {
+    let mut args = args().skip(1);
+    match args.next().as_deref() {
+        Some("list") => list(),
+        Some("run") => run(RunArgs::parse(args)),
+        Some("bench") => bench(BenchArgs::parse(args)),
+        _ => usage(),
+    }
+}
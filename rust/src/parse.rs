@@ -0,0 +1,198 @@
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// An error in the puzzle input, pointing at the offending token. The input
+/// either does not parse, or parses but has no answer.
+///
+/// Lines and columns are counted from 1, as in editors and compiler output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputError {
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub message: String,
+}
+
+impl InputError {
+    pub fn new(line: usize, column: usize, token: &str, message: impl Into<String>) -> Self {
+        return InputError {
+            file: None,
+            line,
+            column,
+            token: token.to_string(),
+            message: message.into(),
+        };
+    }
+
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.file = Some(path.as_ref().to_path_buf());
+        return self;
+    }
+
+    /// Renders the error together with the line of `source` it points at,
+    /// underlining the offending token.
+    pub fn report(&self, source: &str) -> String {
+        let mut report = format!("error: {}\n  --> {}\n", self.message, self.location());
+        if let Some(text) = source.lines().nth(self.line - 1) {
+            let gutter = " ".repeat(self.line.to_string().len());
+            let indent = " ".repeat(self.column - 1);
+            let underline = "^".repeat(self.token.chars().count().max(1));
+            report += &format!("{} |\n{} | {}\n", gutter, self.line, text);
+            report += &format!("{} | {}{}\n", gutter, indent, underline);
+        }
+        return report;
+    }
+
+    fn location(&self) -> String {
+        match &self.file {
+            Some(path) => format!("{}:{}:{}", path.display(), self.line, self.column),
+            None => format!("{}:{}", self.line, self.column),
+        }
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.token.is_empty() {
+            return write!(f, "{}: {}", self.location(), self.message);
+        }
+        return write!(f, "{}: {}: `{}`", self.location(), self.message, self.token);
+    }
+}
+
+impl Error for InputError {}
+
+/// A line of the input together with its line number.
+#[derive(Clone, Copy)]
+pub struct Line<'a> {
+    pub number: usize,
+    pub text: &'a str,
+}
+
+/// A piece of a line together with its position.
+#[derive(Clone, Copy)]
+pub struct Token<'a> {
+    pub line: usize,
+    pub column: usize,
+    pub text: &'a str,
+}
+
+pub fn lines(s: &str) -> impl Iterator<Item = Line<'_>> {
+    return s.lines().enumerate().map(|(i, text)| Line {
+        number: i + 1,
+        text,
+    });
+}
+
+impl<'a> Line<'a> {
+    /// The whole line as a single token.
+    pub fn token(&self) -> Token<'a> {
+        return self.token_at(self.text);
+    }
+
+    /// Splits the line on `sep`, dropping empty pieces, so that repeated
+    /// separators are allowed.
+    pub fn split(&self, sep: char) -> impl DoubleEndedIterator<Item = Token<'a>> + '_ {
+        return self
+            .text
+            .split(sep)
+            .filter(|s| !s.is_empty())
+            .map(|s| self.token_at(s));
+    }
+
+    /// Splits the line at the first occurrence of `sep`.
+    pub fn split_once(&self, sep: char) -> Result<(Token<'a>, Token<'a>), InputError> {
+        match self.text.split_once(sep) {
+            Some((left, right)) => Ok((self.token_at(left), self.token_at(right))),
+            None => Err(self.error(&format!("expected `{}`", sep))),
+        }
+    }
+
+    /// An error pointing at the end of the line, for missing tokens.
+    pub fn error(&self, message: &str) -> InputError {
+        let column = self.text.chars().count() + 1;
+        return InputError::new(self.number, column, "", message);
+    }
+
+    /// `sub` must be a slice of `self.text`.
+    fn token_at(&self, sub: &'a str) -> Token<'a> {
+        let offset = sub.as_ptr() as usize - self.text.as_ptr() as usize;
+        return Token {
+            line: self.number,
+            column: self.text[..offset].chars().count() + 1,
+            text: sub,
+        };
+    }
+}
+
+impl<'a> Token<'a> {
+    pub fn parse<T: FromStr>(&self) -> Result<T, InputError> {
+        return self
+            .text
+            .parse()
+            .map_err(|_| self.error("expected a number"));
+    }
+
+    /// Splits the token on `sep`, dropping empty pieces.
+    pub fn split(&self, sep: char) -> impl DoubleEndedIterator<Item = Token<'a>> + '_ {
+        let line = Line {
+            number: self.line,
+            text: self.text,
+        };
+        let column = self.column;
+        return self
+            .text
+            .split(sep)
+            .filter(|s| !s.is_empty())
+            .map(move |s| {
+                let token = line.token_at(s);
+                Token {
+                    column: token.column + column - 1,
+                    ..token
+                }
+            });
+    }
+
+    pub fn error(&self, message: &str) -> InputError {
+        return InputError::new(self.line, self.column, self.text, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_carry_their_position() {
+        let line = lines("1 2\n3   45").nth(1).unwrap();
+        let tokens: Vec<(usize, usize, &str)> = line
+            .split(' ')
+            .map(|t| (t.line, t.column, t.text))
+            .collect();
+        assert_eq!(tokens, vec![(2, 1, "3"), (2, 5, "45")]);
+    }
+
+    #[test]
+    fn nested_split_keeps_columns() {
+        let line = lines("7: 1 x").next().unwrap();
+        let (_, rhs) = line.split_once(':').unwrap();
+        let err = rhs
+            .split(' ')
+            .map(|t| t.parse::<u64>())
+            .next_back()
+            .unwrap();
+        assert_eq!(err, Err(InputError::new(1, 6, "x", "expected a number")));
+    }
+
+    #[test]
+    fn report_underlines_token() {
+        let err = InputError::new(2, 3, "ab", "expected a number").with_file("in.txt");
+        assert_eq!(
+            err.report("1 2\n3 ab\n"),
+            "error: expected a number\n  --> in.txt:2:3\n  |\n2 | 3 ab\n  |   ^^\n"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::Puzzle;
+use crate::{day1, day2, day3, day4, day5, day6, day7};
+use std::error::Error;
+use std::str::FromStr;
+
+/// Tools fail on malformed input, reported as a `InputError`, or on invalid
+/// options.
+pub type ToolResult = Result<(), Box<dyn Error>>;
+
+/// Options of a tool, given on the command line as `--name` or
+/// `--name=value`.
+pub struct ToolArgs {
+    pub parts: Vec<u32>,
+    pub options: Vec<(String, Option<String>)>,
+}
+
+impl ToolArgs {
+    pub fn flag(&self, name: &str) -> bool {
+        return self.options.iter().any(|(n, _)| n == name);
+    }
+
+    pub fn value<T: FromStr>(&self, name: &str) -> Option<T> {
+        return self
+            .options
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, value)| value.as_deref()?.parse().ok());
+    }
+}
+
+/// A day specific extra, such as a visualisation, that is run on the input
+/// instead of solving it.
+pub struct Tool {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub run: fn(&str, &ToolArgs) -> ToolResult,
+}
+
+pub struct Day {
+    pub day: u32,
+    pub puzzle: &'static dyn Puzzle,
+    pub tools: &'static [Tool],
+}
+
+impl Day {
+    pub fn default_input(&self) -> String {
+        format!("inputs/day{}.txt", self.day)
+    }
+
+    pub fn tool(&self, name: &str) -> Option<&'static Tool> {
+        return self.tools.iter().find(|tool| tool.name == name);
+    }
+}
+
+/// All solved days. Adding a day means adding its module to `lib.rs` and an
+/// entry here.
+pub const DAYS: [Day; 7] = [
+    Day {
+        day: 1,
+        puzzle: &day1::Day1,
+        tools: &[],
+    },
+    Day {
+        day: 2,
+        puzzle: &day2::Day2,
+        tools: &[],
+    },
+    Day {
+        day: 3,
+        puzzle: &day3::Day3,
+        tools: &[],
+    },
+    Day {
+        day: 4,
+        puzzle: &day4::Day4,
+        tools: &[
+            Tool {
+                name: "search",
+                help: "count each of the words given as --words=XMAS,MAS; --highlight prints where they are",
+                run: day4::search,
+            },
+            Tool {
+                name: "stencil",
+                help: "count a pattern like --pattern=M.S/.A./M.S in any orientation; --highlight",
+                run: day4::stencil,
+            },
+        ],
+    },
+    Day {
+        day: 5,
+        puzzle: &day5::Day5,
+        tools: &[
+            Tool {
+                name: "explain",
+                help: "list the rules each update breaks, with the positions of both pages",
+                run: day5::explain,
+            },
+            Tool {
+                name: "repair",
+                help: "compare sorting incorrect updates with moving as few pages as possible",
+                run: day5::repairs,
+            },
+        ],
+    },
+    Day {
+        day: 6,
+        puzzle: &day6::Day6,
+        tools: &[
+            Tool {
+                name: "draw",
+                help: "print the path of the guard; --obstruction=row,col adds an obstruction",
+                run: day6::draw,
+            },
+            Tool {
+                name: "animate",
+                help: "redraw the patrol after every step; --delay=ms, --obstruction=row,col",
+                run: day6::animate,
+            },
+            Tool {
+                name: "loops",
+                help: "list the obstructions that trap the guard, with loop length and entry",
+                run: day6::list_loops,
+            },
+        ],
+    },
+    Day {
+        day: 7,
+        puzzle: &day7::Day7,
+        tools: &[Tool {
+            name: "explain",
+            help: "print an operator choice and the number of solutions per equation",
+            run: day7::explain,
+        }],
+    },
+];
+
+pub fn find(day: u32) -> Option<&'static Day> {
+    return DAYS.iter().find(|d| d.day == day);
+}